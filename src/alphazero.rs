@@ -0,0 +1,430 @@
+use crate::{Board, Field, GameResult, Player};
+use rand::Rng;
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+
+// Small fully connected network with one hidden ReLU layer and two heads:
+// policy logits over every field and a tanh value. Input and value are from the
+// point of view of the side to move: own stones plane, then opponent stones plane.
+#[derive(Clone)]
+pub struct Network {
+    fields: usize,
+    hidden: usize,
+    w1: Vec<f64>,
+    b1: Vec<f64>,
+    wp: Vec<f64>,
+    bp: Vec<f64>,
+    wv: Vec<f64>,
+    bv: f64,
+}
+
+struct Sample {
+    input: Vec<f64>,
+    legal: Vec<bool>,
+    policy: Vec<f64>,
+    value: f64,
+}
+
+fn encode(board: &Board) -> (Vec<f64>, Vec<bool>) {
+    let n = board.fields.len();
+    let mut input = vec![0.0; 2 * n];
+    let mut legal = vec![false; n];
+    for (index, &field) in board.fields.iter().enumerate() {
+        match field {
+            Field::Free => legal[index] = true,
+            Field::Player(p) if p == board.player_turn => input[index] = 1.0,
+            Field::Player(_) => input[n + index] = 1.0,
+        }
+    }
+    (input, legal)
+}
+
+fn softmax(logits: &[f64], legal: &[bool]) -> Vec<f64> {
+    let max = logits
+        .iter()
+        .zip(legal)
+        .filter(|(_, &l)| l)
+        .map(|(&x, _)| x)
+        .fold(f64::MIN, f64::max);
+    let mut result: Vec<f64> = logits
+        .iter()
+        .zip(legal)
+        .map(|(&x, &l)| if l { (x - max).exp() } else { 0.0 })
+        .collect();
+    let sum: f64 = result.iter().sum();
+    for p in result.iter_mut() {
+        *p /= sum;
+    }
+    result
+}
+
+impl Network {
+    pub fn new(fields: usize, hidden: usize) -> Self {
+        let mut rng = rand::thread_rng();
+        let mut init = |len: usize, fan_in: usize| -> Vec<f64> {
+            let scale = (1.0 / fan_in as f64).sqrt();
+            (0..len).map(|_| rng.gen_range(-scale..scale)).collect()
+        };
+        Self {
+            fields,
+            hidden,
+            w1: init(hidden * 2 * fields, 2 * fields),
+            b1: vec![0.0; hidden],
+            wp: init(fields * hidden, hidden),
+            bp: vec![0.0; fields],
+            wv: init(hidden, hidden),
+            bv: 0.0,
+        }
+    }
+
+    fn forward(&self, input: &[f64]) -> (Vec<f64>, Vec<f64>, f64) {
+        let inputs = 2 * self.fields;
+        let hidden: Vec<f64> = (0..self.hidden)
+            .map(|h| {
+                let row = &self.w1[h * inputs..(h + 1) * inputs];
+                let sum: f64 = row.iter().zip(input).map(|(w, x)| w * x).sum();
+                (sum + self.b1[h]).max(0.0)
+            })
+            .collect();
+        let logits: Vec<f64> = (0..self.fields)
+            .map(|f| {
+                let row = &self.wp[f * self.hidden..(f + 1) * self.hidden];
+                row.iter().zip(&hidden).map(|(w, h)| w * h).sum::<f64>() + self.bp[f]
+            })
+            .collect();
+        let value = (self.wv.iter().zip(&hidden).map(|(w, h)| w * h).sum::<f64>() + self.bv).tanh();
+        (hidden, logits, value)
+    }
+
+    // Move priors over all fields (zero for taken ones) and the value for the side to move.
+    pub fn predict(&self, board: &Board) -> (Vec<f64>, f64) {
+        let (input, legal) = encode(board);
+        let (_, logits, value) = self.forward(&input);
+        (softmax(&logits, &legal), value)
+    }
+
+    // One SGD step on (z - v)^2 - pi * log(p) averaged over the batch.
+    fn train_batch(&mut self, batch: &[&Sample], learning_rate: f64) {
+        let inputs = 2 * self.fields;
+        let mut g_w1 = vec![0.0; self.w1.len()];
+        let mut g_b1 = vec![0.0; self.hidden];
+        let mut g_wp = vec![0.0; self.wp.len()];
+        let mut g_bp = vec![0.0; self.fields];
+        let mut g_wv = vec![0.0; self.hidden];
+        let mut g_bv = 0.0;
+
+        for sample in batch {
+            let (hidden, logits, value) = self.forward(&sample.input);
+            let policy = softmax(&logits, &sample.legal);
+            let d_logits: Vec<f64> = policy.iter().zip(&sample.policy).map(|(p, t)| p - t).collect();
+            let d_value = -2.0 * (sample.value - value) * (1.0 - value * value);
+
+            let mut d_hidden = vec![0.0; self.hidden];
+            for f in 0..self.fields {
+                for h in 0..self.hidden {
+                    g_wp[f * self.hidden + h] += d_logits[f] * hidden[h];
+                    d_hidden[h] += d_logits[f] * self.wp[f * self.hidden + h];
+                }
+                g_bp[f] += d_logits[f];
+            }
+            for h in 0..self.hidden {
+                g_wv[h] += d_value * hidden[h];
+                d_hidden[h] += d_value * self.wv[h];
+            }
+            g_bv += d_value;
+
+            for h in 0..self.hidden {
+                if hidden[h] <= 0.0 {
+                    continue;
+                }
+                for i in 0..inputs {
+                    g_w1[h * inputs + i] += d_hidden[h] * sample.input[i];
+                }
+                g_b1[h] += d_hidden[h];
+            }
+        }
+
+        let step = learning_rate / batch.len() as f64;
+        let apply = |weights: &mut [f64], grads: &[f64]| {
+            for (w, g) in weights.iter_mut().zip(grads) {
+                *w -= step * g;
+            }
+        };
+        apply(&mut self.w1, &g_w1);
+        apply(&mut self.b1, &g_b1);
+        apply(&mut self.wp, &g_wp);
+        apply(&mut self.bp, &g_bp);
+        apply(&mut self.wv, &g_wv);
+        self.bv -= step * g_bv;
+    }
+
+    // File format: "fields hidden" on the first line, then one line per weight
+    // group in the order w1, b1, wp, bp, wv, bv.
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut out = format!("{} {}\n", self.fields, self.hidden);
+        for group in [&self.w1, &self.b1, &self.wp, &self.bp, &self.wv, &vec![self.bv]] {
+            let line: Vec<String> = group.iter().map(|w| w.to_string()).collect();
+            out.push_str(&line.join(" "));
+            out.push('\n');
+        }
+        fs::write(path, out)
+    }
+
+    pub fn load(&mut self, path: &str) -> io::Result<()> {
+        let bad_data = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+        let content = fs::read_to_string(path)?;
+        let mut lines = content.lines();
+        if lines.next() != Some(format!("{} {}", self.fields, self.hidden).as_str()) {
+            return Err(bad_data("Checkpoint is for a different network shape"));
+        }
+        let mut groups = vec![];
+        for line in lines {
+            let group: Result<Vec<f64>, _> = line.split(' ').map(|w| w.parse()).collect();
+            groups.push(group.map_err(|_| bad_data("Malformed weight"))?);
+        }
+        let expected = [
+            self.w1.len(),
+            self.b1.len(),
+            self.wp.len(),
+            self.bp.len(),
+            self.wv.len(),
+            1,
+        ];
+        if groups.len() != expected.len() || groups.iter().zip(expected).any(|(g, l)| g.len() != l) {
+            return Err(bad_data("Wrong number of weights"));
+        }
+        self.bv = groups[5][0];
+        self.wv = groups.remove(4);
+        self.bp = groups.remove(3);
+        self.wp = groups.remove(2);
+        self.b1 = groups.remove(1);
+        self.w1 = groups.remove(0);
+        Ok(())
+    }
+}
+
+// value_sum is from the point of view of the side to move at that node
+struct Node {
+    prior: f64,
+    visits: u32,
+    value_sum: f64,
+    children: Vec<(u32, usize)>,
+}
+
+// PUCT Monte Carlo tree search guided by `network`.
+// Returns visit counts of every field after `simulations` playouts from `board`.
+pub fn puct_search(board: &Board, network: &Network, simulations: u32, c_puct: f64) -> Vec<f64> {
+    let mut tree = vec![Node {
+        prior: 1.0,
+        visits: 0,
+        value_sum: 0.0,
+        children: vec![],
+    }];
+    for _ in 0..simulations {
+        let mut board = board.clone();
+        let mut path = vec![0];
+        let mut node = 0;
+        while !tree[node].children.is_empty() {
+            let parent_visits = tree[node].visits as f64;
+            let mut best = tree[node].children[0];
+            let mut best_score = f64::MIN;
+            for &(index, child) in &tree[node].children {
+                let c = &tree[child];
+                let q = if c.visits == 0 { 0.0 } else { -c.value_sum / c.visits as f64 };
+                let u = c_puct * c.prior * parent_visits.sqrt() / (1.0 + c.visits as f64);
+                if q + u > best_score {
+                    best_score = q + u;
+                    best = (index, child);
+                }
+            }
+            board.make_move(best.0, board.size * board.size).unwrap();
+            node = best.1;
+            path.push(node);
+        }
+
+        let mut value = match board.get_result() {
+            // The previous mover completed a line, so the side to move has lost
+            GameResult::Player(_) => -1.0,
+            GameResult::Draw => 0.0,
+            GameResult::InProgress => {
+                let (priors, value) = network.predict(&board);
+                for (index, &prior) in priors.iter().enumerate() {
+                    if board.fields[index] == Field::Free {
+                        tree.push(Node {
+                            prior,
+                            visits: 0,
+                            value_sum: 0.0,
+                            children: vec![],
+                        });
+                        let child = tree.len() - 1;
+                        tree[node].children.push((index as u32, child));
+                    }
+                }
+                value
+            }
+        };
+        for &n in path.iter().rev() {
+            tree[n].visits += 1;
+            tree[n].value_sum += value;
+            value = -value;
+        }
+    }
+
+    let mut visits = vec![0.0; board.fields.len()];
+    for &(index, child) in &tree[0].children {
+        visits[index as usize] = tree[child].visits as f64;
+    }
+    visits
+}
+
+fn sample_move(visits: &[f64]) -> u32 {
+    let total: f64 = visits.iter().sum();
+    let mut pick = rand::thread_rng().gen::<f64>() * total;
+    for (index, &v) in visits.iter().enumerate() {
+        if pick < v {
+            return index as u32;
+        }
+        pick -= v;
+    }
+    visits.iter().rposition(|&v| v > 0.0).unwrap() as u32
+}
+
+fn most_visited(visits: &[f64]) -> u32 {
+    let mut best = 0;
+    for (index, &v) in visits.iter().enumerate() {
+        if v > visits[best] {
+            best = index;
+        }
+    }
+    best as u32
+}
+
+pub struct AlphaZero {
+    pub best: Network,
+    size: u32,
+    win_length: u32,
+    pub simulations: u32,
+    pub c_puct: f64,
+    // Moves sampled from the visit distribution before switching to the most visited
+    pub temperature_moves: usize,
+    pub games_per_iteration: u32,
+    pub buffer_size: usize,
+    pub batch_size: usize,
+    pub train_steps: u32,
+    pub learning_rate: f64,
+    pub evaluation_games: u32,
+    // Score the candidate needs against the best network to replace it
+    pub gate: f64,
+    buffer: VecDeque<Sample>,
+}
+
+impl AlphaZero {
+    pub fn new(size: u32, win_length: u32, hidden: usize) -> Self {
+        Self {
+            best: Network::new((size * size) as usize, hidden),
+            size,
+            win_length,
+            simulations: 100,
+            c_puct: 1.5,
+            temperature_moves: 4,
+            games_per_iteration: 20,
+            buffer_size: 20000,
+            batch_size: 32,
+            train_steps: 200,
+            learning_rate: 0.05,
+            evaluation_games: 20,
+            gate: 0.55,
+            buffer: VecDeque::new(),
+        }
+    }
+
+    pub fn best_move(&self, board: &Board) -> u32 {
+        most_visited(&puct_search(board, &self.best, self.simulations, self.c_puct))
+    }
+
+    fn self_play(&mut self) {
+        let mut board = Board::create_board_k(self.size, self.win_length);
+        let mut history = vec![];
+        while board.get_result() == GameResult::InProgress {
+            let visits = puct_search(&board, &self.best, self.simulations, self.c_puct);
+            let total: f64 = visits.iter().sum();
+            let (input, legal) = encode(&board);
+            history.push((input, legal, visits.iter().map(|v| v / total).collect(), board.player_turn));
+            let index = if board.moves.len() < self.temperature_moves {
+                sample_move(&visits)
+            } else {
+                most_visited(&visits)
+            };
+            board.make_move(index, self.size * self.size).unwrap();
+        }
+        let result = board.get_result();
+        for (input, legal, policy, player) in history {
+            let value = match result {
+                GameResult::Player(winner) if winner == player => 1.0,
+                GameResult::Player(_) => -1.0,
+                _ => 0.0,
+            };
+            if self.buffer.len() == self.buffer_size {
+                self.buffer.pop_front();
+            }
+            self.buffer.push_back(Sample {
+                input,
+                legal,
+                policy,
+                value,
+            });
+        }
+    }
+
+    // Score of `candidate` against the best network, 1 for a win and 0.5 for a draw.
+    fn evaluate(&self, candidate: &Network) -> f64 {
+        let mut score = 0.0;
+        for game in 0..self.evaluation_games {
+            let candidate_turn = if game % 2 == 0 { Player::X } else { Player::O };
+            let mut board = Board::create_board_k(self.size, self.win_length);
+            while board.get_result() == GameResult::InProgress {
+                let network = if board.player_turn == candidate_turn { candidate } else { &self.best };
+                let visits = puct_search(&board, network, self.simulations, self.c_puct);
+                // A couple of sampled opening moves so the games are not all identical
+                let index = if board.moves.len() < 2 {
+                    sample_move(&visits)
+                } else {
+                    most_visited(&visits)
+                };
+                board.make_move(index, self.size * self.size).unwrap();
+            }
+            score += match board.get_result() {
+                GameResult::Player(p) if p == candidate_turn => 1.0,
+                GameResult::Player(_) => 0.0,
+                _ => 0.5,
+            };
+        }
+        score / self.evaluation_games as f64
+    }
+
+    // One generation: self-play into the replay buffer, train a copy of the best
+    // network on it and keep the copy only if it passes the evaluation gate.
+    // Accepted networks are written to `checkpoint`.
+    pub fn iteration(&mut self, checkpoint: &str) -> io::Result<bool> {
+        for _ in 0..self.games_per_iteration {
+            self.self_play();
+        }
+        let mut candidate = self.best.clone();
+        let mut rng = rand::thread_rng();
+        for _ in 0..self.train_steps {
+            let batch: Vec<&Sample> = (0..self.batch_size)
+                .map(|_| &self.buffer[rng.gen_range(0..self.buffer.len())])
+                .collect();
+            candidate.train_batch(&batch, self.learning_rate);
+        }
+        let score = self.evaluate(&candidate);
+        println!("Buffer: {} samples, candidate score: {:.2}", self.buffer.len(), score);
+        if score >= self.gate {
+            self.best = candidate;
+            self.best.save(checkpoint)?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+}
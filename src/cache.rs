@@ -0,0 +1,177 @@
+use crate::{Board, Field, GameResult, Player, RuleSet};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+
+// Bump whenever the meaning of a stored position changes (new rules, different
+// result encoding, ...) so old files are rejected instead of silently misused.
+pub const RULES_VERSION: u8 = 3;
+
+const MAGIC: &[u8; 4] = b"TTTC";
+
+const HEADER_LEN: usize = 17;
+
+// Layout: magic, rules version, width, height, win length, rule flags, entry count (u64
+// little endian), then every entry as the fields packed 2 bits each followed by one
+// result byte. `template` is any board of the game the table belongs to. Loaded entries
+// are taken as the exact result of their position, so `table` must not hold cutoff
+// bounds, pre_alpha_beta_look keeps those out (see exact_score).
+pub fn save(path: &str, template: &Board, table: &HashMap<Board, GameResult>) -> io::Result<()> {
+    let mut out = MAGIC.to_vec();
+    out.extend([
+        RULES_VERSION,
+        template.size as u8,
+        template.height as u8,
+        template.win_length as u8,
+        rule_flags(template),
+    ]);
+    out.extend((table.len() as u64).to_le_bytes());
+    for (board, result) in table {
+        let mut packed = vec![0u8; packed_len(template)];
+        for (index, field) in board.fields.iter().enumerate() {
+            let bits = match field {
+                Field::Free => 0,
+                Field::Player(Player::X) => 1,
+                Field::Player(Player::O) => 2,
+            };
+            packed[index / 4] |= bits << (2 * (index % 4));
+        }
+        out.extend(packed);
+        out.push(result_bits(*result));
+    }
+    fs::write(path, out)
+}
+
+pub fn load(path: &str, template: &Board) -> io::Result<HashMap<Board, GameResult>> {
+    let bad_data = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+    let data = fs::read(path)?;
+    if data.len() < HEADER_LEN || &data[0..4] != MAGIC {
+        return Err(bad_data("Not a position cache"));
+    }
+    if data[4] != RULES_VERSION {
+        return Err(bad_data("Cache was written for a different rules version"));
+    }
+    if data[5] as u32 != template.size || data[6] as u32 != template.height || data[7] as u32 != template.win_length {
+        return Err(bad_data("Cache was written for a different board"));
+    }
+    if data[8] != rule_flags(template) {
+        return Err(bad_data("Cache was written for different win conditions"));
+    }
+    let count = u64::from_le_bytes(data[9..HEADER_LEN].try_into().unwrap()) as usize;
+    let entry_len = packed_len(template) + 1;
+    // A corrupt count must not overflow
+    if count.checked_mul(entry_len).and_then(|n| n.checked_add(HEADER_LEN)) != Some(data.len()) {
+        return Err(bad_data("Cache file is truncated"));
+    }
+
+    let mut table = HashMap::with_capacity(count);
+    for entry in data[HEADER_LEN..].chunks(entry_len) {
+        let mut board = template.empty_like();
+        for index in 0..board.fields.len() {
+            board.fields[index] = match (entry[index / 4] >> (2 * (index % 4))) & 3 {
+                0 => Field::Free,
+                1 => Field::Player(Player::X),
+                2 => Field::Player(Player::O),
+                _ => return Err(bad_data("Unknown field")),
+            };
+        }
+        let result = bits_result(entry[entry_len - 1]).ok_or_else(|| bad_data("Unknown result"))?;
+        table.insert(board, result);
+    }
+    Ok(table)
+}
+
+// Two bit encoding of a result, shared with the flat tables in ranking.rs
+pub fn result_bits(result: GameResult) -> u8 {
+    match result {
+        GameResult::InProgress => 0,
+        GameResult::Draw => 1,
+        GameResult::Player(Player::X) => 2,
+        GameResult::Player(Player::O) => 3,
+    }
+}
+
+pub fn bits_result(bits: u8) -> Option<GameResult> {
+    match bits {
+        0 => Some(GameResult::InProgress),
+        1 => Some(GameResult::Draw),
+        2 => Some(GameResult::Player(Player::X)),
+        3 => Some(GameResult::Player(Player::O)),
+        _ => None,
+    }
+}
+
+pub fn default_path(template: &Board) -> String {
+    let misere = if template.misere { "_misere" } else { "" };
+    let gravity = if template.gravity { "_gravity" } else { "" };
+    let torus = if template.torus { "_torus" } else { "" };
+    let rules = match template.rules {
+        RuleSet::FreeStyle => "",
+        RuleSet::Standard => "_standard",
+        RuleSet::Renju => "_renju",
+    };
+    format!(
+        "cache_{}x{}_k{}{}{}{}{}.bin",
+        template.size, template.height, template.win_length, misere, gravity, torus, rules
+    )
+}
+
+fn rule_flags(template: &Board) -> u8 {
+    template.misere as u8 | (template.gravity as u8) << 1 | (template.torus as u8) << 2 | (template.rules as u8) << 3
+}
+
+fn packed_len(template: &Board) -> usize {
+    template.fields.len().div_ceil(4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::retrograde::Database;
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("{}_{}.bin", name, std::process::id()));
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn save_load_round_trip() {
+        let database = Database::solve(Board::create_board(3));
+        let table: HashMap<Board, GameResult> = database.boards().map(|(b, e)| (b.clone(), e.result)).collect();
+        let path = temp_path("cache_round_trip");
+        save(&path, &database.start, &table).unwrap();
+        let loaded = load(&path, &database.start);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), table);
+    }
+
+    #[test]
+    fn bad_headers_are_rejected() {
+        let template = Board::create_board(3);
+        let mut table = HashMap::new();
+        table.insert(template.clone(), GameResult::Draw);
+        let path = temp_path("cache_bad_header");
+        save(&path, &template, &table).unwrap();
+        let good = fs::read(&path).unwrap();
+
+        let mut corruptions: Vec<Vec<u8>> = vec![good[..HEADER_LEN - 1].to_vec(), good[..good.len() - 1].to_vec()];
+        for (index, value) in [(0, b'X'), (4, RULES_VERSION + 1), (5, 4), (8, 1)] {
+            let mut data = good.clone();
+            data[index] = value;
+            corruptions.push(data);
+        }
+        // Entry counts that overflow the expected length
+        for count in [u64::MAX, u64::MAX / 3] {
+            let mut data = good.clone();
+            data[9..HEADER_LEN].copy_from_slice(&count.to_le_bytes());
+            corruptions.push(data);
+        }
+        for data in corruptions {
+            fs::write(&path, data).unwrap();
+            let error = load(&path, &template).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+        fs::remove_file(&path).unwrap();
+        assert!(load(&path, &template).is_err());
+    }
+}
@@ -0,0 +1,290 @@
+use crate::rules::GameRules;
+use crate::{lines, Board, Field, GameResult, Player};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+// One turn: the opening single stone, or two stones everywhere after it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Connect6Move {
+    Single(u32),
+    Double(u32, u32),
+}
+
+impl Connect6Move {
+    pub fn stones(self) -> Vec<u32> {
+        match self {
+            Connect6Move::Single(field) => vec![field],
+            Connect6Move::Double(first, second) => vec![first, second],
+        }
+    }
+}
+
+// Connect6: X opens with one stone, then both players place two stones a turn, six in
+// a row wins. Each turn goes on the Board with make_turn, so undo takes back a whole
+// turn.
+#[derive(Clone)]
+pub struct Connect6 {
+    pub board: Board,
+    // Fields the search pairs up, the best ones by field_score
+    pub candidates: usize,
+    // Shared between all clones, both only depend on the size
+    lines: Rc<Vec<Vec<usize>>>,
+    // Indices into `lines` of the lines through every field
+    lines_through: Rc<Vec<Vec<usize>>>,
+}
+
+// The stone count decides whose turn it is
+impl PartialEq for Connect6 {
+    fn eq(&self, other: &Self) -> bool {
+        self.board == other.board
+    }
+}
+
+impl Eq for Connect6 {}
+
+impl Hash for Connect6 {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.board.hash(state);
+    }
+}
+
+impl Connect6 {
+    pub fn create_game(n: u32, k: u32) -> Self {
+        let lines = lines(n, n, k);
+        let mut lines_through = vec![vec![]; (n * n) as usize];
+        for (index, line) in lines.iter().enumerate() {
+            for &field in line {
+                lines_through[field].push(index);
+            }
+        }
+        Self {
+            board: Board::create_board_k(n, k),
+            candidates: 8,
+            lines: Rc::new(lines),
+            lines_through: Rc::new(lines_through),
+        }
+    }
+
+    // Stones of `player` and of the opponent on a line
+    fn counts(&self, line: &[usize], player: Player) -> (u32, u32) {
+        let mut own = 0;
+        let mut other = 0;
+        for &field in line {
+            match self.board.fields[field] {
+                Field::Player(p) if p == player => own += 1,
+                Field::Player(_) => other += 1,
+                Field::Free => {}
+            }
+        }
+        (own, other)
+    }
+
+    // Open lines through `field`, worth more the more stones they hold, own lines a bit
+    // more than the opponent's ones
+    fn field_score(&self, field: usize, player: Player) -> u64 {
+        let mut score = 0;
+        for &index in &self.lines_through[field] {
+            match self.counts(&self.lines[index], player) {
+                (own, 0) => score += 1 << (2 * own + 1),
+                (0, other) => score += 1 << (2 * other),
+                _ => {}
+            }
+        }
+        score
+    }
+
+    // Free fields of a line that only needs the stones of one turn to be complete
+    fn winning_fields(&self, player: Player) -> Option<Vec<u32>> {
+        let k = self.board.win_length;
+        self.lines.iter().find_map(|line| match self.counts(line, player) {
+            (own, 0) if own + 2 >= k => {
+                Some(line.iter().filter(|&&f| self.board.fields[f] == Field::Free).map(|&f| f as u32).collect())
+            }
+            _ => None,
+        })
+    }
+
+    // Free fields at most two steps from a stone, every free field on an empty board
+    fn near_stones(&self) -> Vec<usize> {
+        let free: Vec<usize> =
+            (0..self.board.fields.len()).filter(|&f| self.board.fields[f] == Field::Free).collect();
+        if self.board.moves.is_empty() {
+            return free;
+        }
+        free.into_iter()
+            .filter(|&f| {
+                (-2..=2).any(|dr| {
+                    (-2..=2).any(|dc| {
+                        self.board
+                            .step(f, (dr, dc), 1)
+                            .is_some_and(|n| self.board.fields[n] != Field::Free)
+                    })
+                })
+            })
+            .collect()
+    }
+
+    fn single_stone_turn(&self) -> bool {
+        self.board.moves.is_empty() || self.board.fields.iter().filter(|&&f| f == Field::Free).count() == 1
+    }
+}
+
+impl GameRules for Connect6 {
+    type Move = Connect6Move;
+
+    // Every pair of free fields, ordered_moves is what the search uses
+    fn legal_moves(&self) -> Vec<Connect6Move> {
+        let free = self.board.free_moves();
+        if self.single_stone_turn() {
+            return free.into_iter().map(Connect6Move::Single).collect();
+        }
+        let mut moves = vec![];
+        for (i, &first) in free.iter().enumerate() {
+            for &second in &free[i + 1..] {
+                moves.push(Connect6Move::Double(first, second));
+            }
+        }
+        moves
+    }
+
+    fn apply(&mut self, m: Connect6Move) -> Result<(), &'static str> {
+        let len = self.board.size * self.board.size;
+        match m {
+            Connect6Move::Single(_) if !self.single_stone_turn() => return Err("Two stones have to be placed"),
+            Connect6Move::Double(..) if self.single_stone_turn() => return Err("Only one stone can be placed"),
+            Connect6Move::Double(first, second) if first == second => return Err("Field is already taken!"),
+            _ => {}
+        }
+        self.board.make_turn(&m.stones(), len)
+    }
+
+    fn undo(&mut self) -> Result<(), &'static str> {
+        self.board.undo_last_move()
+    }
+
+    // Only the stones of the last turn can have completed a line
+    fn result(&self) -> GameResult {
+        if let Some(&stones) = self.board.turns.last() {
+            let mover = self.board.player_turn.opponent();
+            for &field in &self.board.moves[self.board.moves.len() - stones..] {
+                for &index in &self.lines_through[field as usize] {
+                    if self.lines[index].iter().all(|&f| self.board.fields[f] == Field::Player(mover)) {
+                        return GameResult::Player(mover);
+                    }
+                }
+            }
+        }
+        if self.board.fields.contains(&Field::Free) {
+            GameResult::InProgress
+        } else {
+            GameResult::Draw
+        }
+    }
+
+    fn side_to_move(&self) -> Player {
+        self.board.player_turn
+    }
+
+    // Pruned: a win when there is one, otherwise only the `candidates` best fields near
+    // the stones (at least two, so they make a pair), paired up best first. Everything
+    // legal when pruning leaves nothing.
+    fn ordered_moves(&self) -> Vec<Connect6Move> {
+        let player = self.board.player_turn;
+        let mut fields: Vec<(usize, u64)> =
+            self.near_stones().into_iter().map(|f| (f, self.field_score(f, player))).collect();
+        fields.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+        fields.truncate(self.candidates.max(2));
+        if self.single_stone_turn() {
+            return fields.into_iter().map(|(f, _)| Connect6Move::Single(f as u32)).collect();
+        }
+        if let Some(mut winning) = self.winning_fields(player) {
+            let filler = fields.iter().map(|&(f, _)| f as u32).find(|f| !winning.contains(f));
+            if winning.len() == 1 {
+                winning.extend(filler);
+            }
+            if let [first, second] = winning[..] {
+                return vec![Connect6Move::Double(first, second)];
+            }
+        }
+        let mut pairs = vec![];
+        for (i, &(first, first_score)) in fields.iter().enumerate() {
+            for &(second, second_score) in &fields[i + 1..] {
+                pairs.push((Connect6Move::Double(first as u32, second as u32), first_score + second_score));
+            }
+        }
+        if pairs.is_empty() {
+            return self.legal_moves();
+        }
+        pairs.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+        pairs.into_iter().map(|(m, _)| m).collect()
+    }
+}
+
+// Move picking player, for matches between the AIs
+pub type Engine<'a> = &'a dyn Fn(&Connect6) -> Connect6Move;
+
+// Lines only one player still has stones on, worth 4^stones, X minus O
+pub fn connect6_eval(game: &Connect6) -> i32 {
+    let mut result = 0;
+    for line in game.lines.iter() {
+        match game.counts(line, Player::X) {
+            (0, 0) => {}
+            (x, 0) => result += 1 << (2 * x),
+            (0, o) => result -= 1 << (2 * o),
+            _ => {}
+        }
+    }
+    result
+}
+
+impl fmt::Debug for Connect6 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.board)?;
+        let stones = if self.single_stone_turn() { 1 } else { 2 };
+        write!(f, "{:?} to move, {} stone(s)", self.board.player_turn, stones)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::best_move_depth;
+    use crate::rules::tests::undo_round_trip;
+
+    #[test]
+    fn too_few_candidates_still_give_moves() {
+        for candidates in 0..3 {
+            let mut game = Connect6::create_game(9, 6);
+            game.candidates = candidates;
+            for _ in 0..10 {
+                assert!(!game.ordered_moves().is_empty());
+                let m = best_move_depth(&game, 2, &connect6_eval);
+                game.apply(m).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn undo_takes_back_whole_turns() {
+        let game = Connect6::create_game(9, 6);
+        undo_round_trip(&game, 10, |g| (g.board.fields.clone(), g.board.moves.clone(), g.side_to_move()));
+    }
+
+    #[test]
+    fn board_keeps_turns_together() {
+        let mut game = Connect6::create_game(9, 6);
+        game.apply(Connect6Move::Single(40)).unwrap();
+        game.apply(Connect6Move::Double(41, 42)).unwrap();
+        assert_eq!(game.board.turns, vec![1, 2]);
+        // A turn that fails halfway leaves nothing behind
+        assert_eq!(game.apply(Connect6Move::Double(43, 40)), Err("Field is already taken!"));
+        assert_eq!(game.board.moves, vec![40, 41, 42]);
+        assert_eq!(game.board.fields[43], Field::Free);
+        assert_eq!(game.side_to_move(), Player::X);
+        game.board.undo_last_move().unwrap();
+        assert_eq!(game.board.moves, vec![40]);
+        assert_eq!(game.board.turns, vec![1]);
+        assert_eq!(game.side_to_move(), Player::O);
+    }
+}
@@ -0,0 +1,298 @@
+use crate::rules::GameRules;
+use crate::{Field, GameResult, Player};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+// n x n x n tic-tac-toe (Qubic for n = 4). Field index is layer * n * n + row * n + column,
+// a line has to span the whole cube.
+#[derive(Clone)]
+pub struct Cube {
+    pub size: usize,
+    fields: Vec<Field>,
+    player_turn: Player,
+    moves: Vec<usize>,
+    // Shared between all clones, both only depend on the size
+    lines: Rc<Vec<Vec<usize>>>,
+    symmetries: Rc<Vec<Vec<usize>>>,
+}
+
+impl PartialEq for Cube {
+    fn eq(&self, other: &Self) -> bool {
+        self.fields == other.fields
+    }
+}
+
+impl Eq for Cube {}
+
+impl Hash for Cube {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.fields.hash(state);
+    }
+}
+
+// Every line of n fields: one per direction (out of the 13 up to sign) and start field
+// from which n steps stay inside the cube. 3 * n^2 rows and columns, 6 * n plane
+// diagonals and 4 space diagonals, 76 lines for n = 4 and 49 for n = 3.
+pub fn cube_lines(n: usize) -> Vec<Vec<usize>> {
+    let n = n as i32;
+    let mut lines = vec![];
+    for dz in -1..=1 {
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                // Count every direction once, the first non zero step is positive
+                let first = [dz, dy, dx].into_iter().find(|&d| d != 0);
+                if first != Some(1) {
+                    continue;
+                }
+                for z in 0..n {
+                    for y in 0..n {
+                        for x in 0..n {
+                            let end = (z + dz * (n - 1), y + dy * (n - 1), x + dx * (n - 1));
+                            let inside = |c: i32| (0..n).contains(&c);
+                            if !inside(end.0) || !inside(end.1) || !inside(end.2) {
+                                continue;
+                            }
+                            let line = (0..n)
+                                .map(|i| (((z + dz * i) * n + y + dy * i) * n + x + dx * i) as usize)
+                                .collect();
+                            lines.push(line);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    lines
+}
+
+// The 48 symmetries of the cube, every permutation of the axes combined with every
+// mirroring of them. Entry i of a symmetry is where field i ends up, the first one is
+// the identity.
+pub fn cube_symmetries(n: usize) -> Vec<Vec<usize>> {
+    let axis_orders = [[0, 1, 2], [0, 2, 1], [1, 0, 2], [1, 2, 0], [2, 0, 1], [2, 1, 0]];
+    let mut result = vec![];
+    for order in axis_orders {
+        for mirror in 0..8 {
+            let symmetry = (0..n * n * n)
+                .map(|index| {
+                    let coords = [index / (n * n), index / n % n, index % n];
+                    let mut moved = [0; 3];
+                    for axis in 0..3 {
+                        let c = coords[order[axis]];
+                        moved[axis] = if mirror & (1 << axis) != 0 { n - 1 - c } else { c };
+                    }
+                    (moved[0] * n + moved[1]) * n + moved[2]
+                })
+                .collect();
+            result.push(symmetry);
+        }
+    }
+    result
+}
+
+impl Cube {
+    pub fn create_cube(n: usize) -> Self {
+        Self {
+            size: n,
+            fields: vec![Field::Free; n * n * n],
+            player_turn: Player::X,
+            moves: vec![],
+            lines: Rc::new(cube_lines(n)),
+            symmetries: Rc::new(cube_symmetries(n)),
+        }
+    }
+
+    pub fn lines(&self) -> &[Vec<usize>] {
+        &self.lines
+    }
+
+    // Lines still open for `player` minus lines still open for the opponent
+    fn lines_heuristic(&self, player: Player) -> i32 {
+        let mut result = 0;
+        for line in self.lines.iter() {
+            let own = line.iter().any(|&f| self.fields[f] == Field::Player(player));
+            let other = line.iter().any(|&f| self.fields[f] == Field::Player(player.opponent()));
+            match (own, other) {
+                (true, false) => result += 1,
+                (false, true) => result -= 1,
+                _ => {}
+            }
+        }
+        result
+    }
+}
+
+impl GameRules for Cube {
+    type Move = usize;
+
+    fn legal_moves(&self) -> Vec<usize> {
+        (0..self.fields.len()).filter(|&f| self.fields[f] == Field::Free).collect()
+    }
+
+    fn apply(&mut self, m: usize) -> Result<(), &'static str> {
+        match self.fields.get(m) {
+            None => Err("Wrong square number"),
+            Some(Field::Player(_)) => Err("Field is already taken!"),
+            Some(Field::Free) => {
+                self.fields[m] = Field::Player(self.player_turn);
+                self.moves.push(m);
+                self.player_turn = self.player_turn.opponent();
+                Ok(())
+            }
+        }
+    }
+
+    fn undo(&mut self) -> Result<(), &'static str> {
+        let last_move = self.moves.pop().ok_or("No moves has been played")?;
+        self.fields[last_move] = Field::Free;
+        self.player_turn = self.player_turn.opponent();
+        Ok(())
+    }
+
+    fn result(&self) -> GameResult {
+        for line in self.lines.iter() {
+            if let Field::Player(owner) = self.fields[line[0]] {
+                if line.iter().all(|&f| self.fields[f] == Field::Player(owner)) {
+                    return GameResult::Player(owner);
+                }
+            }
+        }
+        if self.fields.contains(&Field::Free) {
+            GameResult::InProgress
+        } else {
+            GameResult::Draw
+        }
+    }
+
+    fn side_to_move(&self) -> Player {
+        self.player_turn
+    }
+
+    // Winning moves first, then blocks of the opponent's wins, then fields on many
+    // open lines
+    fn ordered_moves(&self) -> Vec<usize> {
+        let player = self.player_turn;
+        let score = |m: usize| {
+            let mut score = 0;
+            for line in self.lines.iter().filter(|l| l.contains(&m)) {
+                let own = line.iter().filter(|&&f| self.fields[f] == Field::Player(player)).count();
+                let other = line.iter().filter(|&&f| self.fields[f] == Field::Player(player.opponent())).count();
+                score += match (own, other) {
+                    (own, 0) if own == self.size - 1 => 10000,
+                    (0, other) if other == self.size - 1 => 1000,
+                    (0, _) | (_, 0) => 1,
+                    _ => 0,
+                };
+            }
+            score
+        };
+        let mut moves = self.legal_moves();
+        moves.sort_by_key(|&m| std::cmp::Reverse(score(m)));
+        moves
+    }
+
+    fn symmetries(&self) -> Vec<Self> {
+        self.symmetries[1..]
+            .iter()
+            .map(|symmetry| {
+                let mut fields = vec![Field::Free; self.fields.len()];
+                for (index, &field) in self.fields.iter().enumerate() {
+                    fields[symmetry[index]] = field;
+                }
+                Self {
+                    fields,
+                    moves: vec![],
+                    ..self.clone()
+                }
+            })
+            .collect()
+    }
+}
+
+pub fn cube_eval(cube: &Cube) -> i32 {
+    cube.lines_heuristic(Player::X)
+}
+
+// Layers next to each other, the top layer (0) on the left
+impl fmt::Debug for Cube {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let n = self.size;
+        let width = 4 * n - 1;
+        for layer in 0..n {
+            write!(f, "{:<width$}   ", format!("Layer {}", layer), width = width)?;
+        }
+        writeln!(f)?;
+        for row in 0..n {
+            for layer in 0..n {
+                let cells: Vec<String> = (0..n)
+                    .map(|column| format!(" {:?} ", self.fields[(layer * n + row) * n + column]))
+                    .collect();
+                write!(f, "{}   ", cells.join("|"))?;
+            }
+            writeln!(f)?;
+            if row != n - 1 {
+                for _ in 0..n {
+                    write!(f, "{}   ", "-".repeat(width))?;
+                }
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::tests::undo_round_trip;
+    use std::collections::HashSet;
+
+    #[test]
+    fn line_counts() {
+        assert_eq!(cube_lines(3).len(), 49);
+        assert_eq!(cube_lines(4).len(), 76);
+    }
+
+    // Every symmetry is a different permutation that takes lines to lines
+    #[test]
+    fn symmetries_keep_the_lines() {
+        for n in [3, 4] {
+            let lines: HashSet<Vec<usize>> = cube_lines(n)
+                .into_iter()
+                .map(|mut line| {
+                    line.sort();
+                    line
+                })
+                .collect();
+            let symmetries = cube_symmetries(n);
+            assert_eq!(symmetries.len(), 48);
+            assert_eq!(symmetries.iter().collect::<HashSet<_>>().len(), 48);
+            assert_eq!(symmetries[0], (0..n * n * n).collect::<Vec<_>>());
+            for symmetry in &symmetries {
+                for line in &lines {
+                    let mut moved: Vec<usize> = line.iter().map(|&f| symmetry[f]).collect();
+                    moved.sort();
+                    assert!(lines.contains(&moved));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn space_diagonal_wins() {
+        let mut cube = Cube::create_cube(4);
+        for m in [0, 1, 21, 2, 42] {
+            cube.apply(m).unwrap();
+        }
+        assert_eq!(cube.result(), GameResult::InProgress);
+        cube.apply(3).unwrap();
+        cube.apply(63).unwrap();
+        assert_eq!(cube.result(), GameResult::Player(Player::X));
+    }
+
+    #[test]
+    fn undo_frees_the_field() {
+        undo_round_trip(&Cube::create_cube(3), 50, |c| c.fields.clone());
+    }
+}
@@ -0,0 +1,169 @@
+use crate::rules::GameRules;
+use crate::{Board, Field, GameResult, Player};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+// Tic-tac-toe where each player has at most `marks` marks on the board, placing one
+// more takes away that player's oldest one. Players alternate, so the mark that goes
+// is the one placed 2 * marks plies earlier in `board.moves`. Games can go round in
+// circles, so a position that comes back `repetitions` times, or reaching
+// `move_limit` plies, is a draw.
+#[derive(Clone)]
+pub struct Disappearing {
+    pub board: Board,
+    pub marks: usize,
+    pub repetitions: usize,
+    pub move_limit: usize,
+}
+
+// The marks still on the board in the order they were placed, which also decides
+// which of them go first, together with the side to move
+impl PartialEq for Disappearing {
+    fn eq(&self, other: &Self) -> bool {
+        self.window(self.board.moves.len()) == other.window(other.board.moves.len())
+            && self.board.player_turn == other.board.player_turn
+    }
+}
+
+impl Eq for Disappearing {}
+
+impl Hash for Disappearing {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.window(self.board.moves.len()).hash(state);
+        self.board.player_turn.hash(state);
+    }
+}
+
+impl Disappearing {
+    pub fn create_game(n: u32, marks: usize) -> Self {
+        // With no marks there is never one to take away
+        assert!(marks > 0, "Each player needs at least one mark");
+        Self {
+            board: Board::create_board(n),
+            marks,
+            repetitions: 3,
+            move_limit: 100,
+        }
+    }
+
+    // Placements still on the board after `ply` plies
+    fn window(&self, ply: usize) -> &[u32] {
+        &self.board.moves[ply.saturating_sub(2 * self.marks)..ply]
+    }
+
+    // Field of the mark the next move takes away
+    fn next_to_go(&self) -> Option<u32> {
+        let ply = self.board.moves.len();
+        (ply >= 2 * self.marks).then(|| self.board.moves[ply - 2 * self.marks])
+    }
+
+    // How often the current position was on the board, this time included. Only plies
+    // with the same side to move can hold it.
+    pub fn occurrences(&self) -> usize {
+        let ply = self.board.moves.len();
+        (ply % 2..=ply)
+            .step_by(2)
+            .filter(|&earlier| self.window(earlier) == self.window(ply))
+            .count()
+    }
+}
+
+impl GameRules for Disappearing {
+    type Move = u32;
+
+    fn legal_moves(&self) -> Vec<u32> {
+        self.board.free_moves()
+    }
+
+    fn apply(&mut self, m: u32) -> Result<(), &'static str> {
+        let gone = self.next_to_go();
+        self.board.make_move(m, self.board.size * self.board.size)?;
+        if let Some(field) = gone {
+            self.board.fields[field as usize] = Field::Free;
+        }
+        Ok(())
+    }
+
+    fn undo(&mut self) -> Result<(), &'static str> {
+        let mover = self.board.player_turn.opponent();
+        self.board.undo_last_move()?;
+        if let Some(field) = self.next_to_go() {
+            self.board.fields[field as usize] = Field::Player(mover);
+        }
+        Ok(())
+    }
+
+    fn result(&self) -> GameResult {
+        match self.board.get_result() {
+            GameResult::InProgress
+                if self.occurrences() >= self.repetitions || self.board.moves.len() >= self.move_limit =>
+            {
+                GameResult::Draw
+            }
+            result => result,
+        }
+    }
+
+    fn side_to_move(&self) -> Player {
+        self.board.player_turn
+    }
+}
+
+// Move picking player, for matches between the AIs
+pub type Engine<'a> = &'a dyn Fn(&Disappearing) -> u32;
+
+pub fn disappearing_eval(game: &Disappearing) -> i32 {
+    game.board.lines_heuristic(Player::X)
+}
+
+impl fmt::Debug for Disappearing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.board)?;
+        if let Some(field) = self.next_to_go() {
+            writeln!(f, "Next move takes away the {:?} on {}", self.board.player_turn, field)?;
+        }
+        write!(f, "{:?} to move, ply {}", self.board.player_turn, self.board.moves.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::tests::undo_round_trip;
+
+    // Undo has to bring back the marks that went
+    #[test]
+    fn undo_brings_marks_back() {
+        undo_round_trip(&Disappearing::create_game(3, 3), 200, |g| g.board.fields.clone());
+    }
+
+    #[test]
+    fn oldest_mark_goes() {
+        let mut game = Disappearing::create_game(3, 2);
+        for m in [0, 4, 8, 2] {
+            game.apply(m).unwrap();
+        }
+        game.apply(6).unwrap();
+        assert_eq!(game.board.fields[0], Field::Free);
+        assert_eq!(game.board.fields[6], Field::Player(Player::X));
+        assert_eq!(game.board.fields.iter().filter(|&&f| f != Field::Free).count(), 4);
+    }
+
+    // Moving back and forth between two fields brings the same positions back
+    #[test]
+    fn repetition_is_a_draw() {
+        let mut game = Disappearing::create_game(3, 1);
+        let mut moves = [0, 8, 1, 7].iter().cycle();
+        while game.result() == GameResult::InProgress {
+            game.apply(*moves.next().unwrap()).unwrap();
+        }
+        assert_eq!(game.result(), GameResult::Draw);
+        assert_eq!(game.occurrences(), game.repetitions);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one mark")]
+    fn no_marks_is_rejected() {
+        Disappearing::create_game(3, 0);
+    }
+}
@@ -1,11 +1,35 @@
-use core::{fmt, panic};
+use core::fmt;
 use rand::Rng;
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 use std::io;
+use std::sync::atomic::{self, AtomicBool};
 use std::time::{Duration, Instant};
 
+use rules::GameRules;
+
+mod alphazero;
+mod cache;
+mod connect6;
+mod cube;
+mod disappearing;
+mod mcts;
+mod morris;
+mod notakto;
+mod ntuple;
+mod order_chaos;
+mod perft;
+mod ponder;
+mod qlearning;
+mod quantum;
+mod ranking;
+mod retrograde;
+mod rules;
+mod stats;
+mod ultimate;
+mod wild;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Player {
     X,
@@ -79,6 +103,27 @@ impl Ord for GameResult {
     }
 }
 
+// How to choose between moves that have the same GameResult
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TieBreak {
+    // Keep the first best move found
+    First,
+    // Prefer the move where most of the opponent replies are mistakes
+    Exploit,
+}
+
+// What a row of win_length counts as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum RuleSet {
+    // win_length or more in a row wins
+    FreeStyle,
+    // Exactly win_length, longer rows (overlines) don't win
+    Standard,
+    // Exactly win_length for X, who moves first and can't make a double three, a double
+    // four or an overline. O wins with win_length or more.
+    Renju,
+}
+
 #[derive(Clone, Copy, PartialEq, Hash)]
 enum Field {
     Player(Player),
@@ -102,12 +147,28 @@ struct Board {
     fields: Vec<Field>,
     player_turn: Player,
     moves: Vec<u32>,
+    // Stones placed in each turn, one per move unless make_turn places more
+    turns: Vec<usize>,
+    // Number of columns, and of rows unless `height` says otherwise
     size: u32,
+    height: u32,
+    win_length: u32,
+    // Completing a line loses instead of winning
+    misere: bool,
+    // Pieces drop to the lowest free field of a column, moves are column numbers
+    gravity: bool,
+    // Rows, columns and diagonals wrap around the edges
+    torus: bool,
+    rules: RuleSet,
 }
 
 impl PartialEq for Board {
     fn eq(&self, other: &Self) -> bool {
         self.fields == other.fields
+            && self.misere == other.misere
+            && self.gravity == other.gravity
+            && self.torus == other.torus
+            && self.rules == other.rules
     }
 }
 
@@ -116,7 +177,67 @@ impl Eq for Board {}
 impl Hash for Board {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.fields.hash(state);
+        self.misere.hash(state);
+        self.gravity.hash(state);
+        self.torus.hash(state);
+        self.rules.hash(state);
+    }
+}
+
+// (row, column) steps of the horizontal, vertical and both diagonal directions
+const DIRECTIONS: [(i32, i32); 4] = [(0, 1), (1, 0), (1, 1), (1, -1)];
+
+// Every run of `length` consecutive fields on a `size` x `size` board,
+// horizontal, vertical and both diagonal directions.
+fn lines(width: u32, height: u32, length: u32) -> Vec<Vec<usize>> {
+    let n = width as i32;
+    let rows = height as i32;
+    let k = length as i32;
+    let mut result = vec![];
+    for (dr, dc) in DIRECTIONS {
+        for row in 0..rows {
+            for col in 0..n {
+                let end_row = row + dr * (k - 1);
+                let end_col = col + dc * (k - 1);
+                if end_row >= rows || end_col < 0 || end_col >= n {
+                    continue;
+                }
+                result.push(
+                    (0..k)
+                        .map(|i| ((row + dr * i) * n + col + dc * i) as usize)
+                        .collect(),
+                );
+            }
+        }
+    }
+    result
+}
+
+// Lines of `length` fields on a torus, every start field in every direction with the
+// steps taken modulo the board size. Lines that would visit a field twice don't count,
+// and when `length` is the whole period of a direction the same line comes back from
+// every start on it, so it's only kept once.
+fn torus_lines(width: u32, height: u32, length: u32) -> Vec<Vec<usize>> {
+    let n = width as i32;
+    let rows = height as i32;
+    let mut seen = HashSet::new();
+    let mut result = vec![];
+    for (dr, dc) in DIRECTIONS {
+        for row in 0..rows {
+            for col in 0..n {
+                let line: Vec<usize> = (0..length as i32)
+                    .map(|i| ((row + dr * i).rem_euclid(rows) * n + (col + dc * i).rem_euclid(n)) as usize)
+                    .collect();
+                let mut sorted = line.clone();
+                sorted.sort();
+                sorted.dedup();
+                if sorted.len() == line.len() && seen.insert(sorted) {
+                    result.push(line);
+                }
+            }
+        }
     }
+    result
 }
 
 impl Board {
@@ -125,181 +246,125 @@ impl Board {
             fields: vec![Field::Free; (n * n) as usize],
             player_turn: Player::X,
             moves: vec![],
+            turns: vec![],
             size: n,
+            height: n,
+            win_length: n,
+            misere: false,
+            gravity: false,
+            torus: false,
+            rules: RuleSet::FreeStyle,
+        }
+    }
+
+    fn create_board_k(n: u32, k: u32) -> Self {
+        Self {
+            win_length: k,
+            ..Self::create_board(n)
+        }
+    }
+
+    fn create_misere_board(n: u32, k: u32) -> Self {
+        Self {
+            misere: true,
+            ..Self::create_board_k(n, k)
         }
     }
 
+    fn create_gravity_board(width: u32, height: u32, k: u32) -> Self {
+        Self {
+            fields: vec![Field::Free; (width * height) as usize],
+            height,
+            gravity: true,
+            ..Self::create_board_k(width, k)
+        }
+    }
+
+    fn create_gomoku_board(n: u32, rules: RuleSet) -> Self {
+        Self {
+            rules,
+            ..Self::create_board_k(n, 5)
+        }
+    }
+
+    // Empty board with the same size and rules
+    fn empty_like(&self) -> Self {
+        Self {
+            fields: vec![Field::Free; self.fields.len()],
+            player_turn: Player::X,
+            moves: vec![],
+            turns: vec![],
+            ..self.clone()
+        }
+    }
+
+    fn winning_lines(&self) -> Vec<Vec<usize>> {
+        if self.torus {
+            return torus_lines(self.size, self.height, self.win_length);
+        }
+        lines(self.size, self.height, self.win_length)
+    }
+
     fn get_result(&self) -> GameResult {
-        // TODO:Create this map once when creating game, and pass immutable reference to this fn
-        let mut winner_combinations = HashMap::new();
-        winner_combinations.insert(
-            2,
-            vec![
-                // Horizontal lines
-                vec![0, 1],
-                vec![2, 3],
-                // Vertical lines
-                vec![0, 2],
-                vec![1, 3],
-                // Diagonal lines
-                vec![0, 3],
-                vec![1, 2],
-            ],
-        );
-        winner_combinations.insert(
-            3,
-            vec![
-                // Horizontal lines
-                vec![0, 1, 2],
-                vec![3, 4, 5],
-                vec![6, 7, 8],
-                // Vertical lines
-                vec![0, 3, 6],
-                vec![1, 4, 7],
-                vec![2, 5, 8],
-                // Diagonal lines
-                vec![0, 4, 8],
-                vec![2, 4, 6],
-            ],
-        );
-        winner_combinations.insert(
-            4,
-            vec![
-                // Horizontal lines
-                vec![0, 1, 2, 3],
-                vec![4, 5, 6, 7],
-                vec![8, 9, 10, 11],
-                vec![12, 13, 14, 15],
-                // Vertical lines
-                vec![0, 4, 8, 12],
-                vec![1, 5, 9, 13],
-                vec![2, 6, 10, 14],
-                vec![3, 7, 11, 15],
-                // Diagonal lines
-                vec![0, 5, 10, 15],
-                vec![3, 6, 9, 12],
-            ],
-        );
-        winner_combinations.insert(
-            5,
-            vec![
-                // Horizontal lines
-                vec![0, 1, 2, 3, 4],
-                vec![5, 6, 7, 8, 9],
-                vec![10, 11, 12, 13, 14],
-                vec![15, 16, 17, 18, 19],
-                vec![20, 21, 22, 23, 24],
-                // Vertical lines
-                vec![0, 5, 10, 15, 20],
-                vec![1, 6, 11, 16, 21],
-                vec![2, 7, 12, 17, 22],
-                vec![3, 8, 13, 18, 23],
-                vec![4, 9, 14, 19, 24],
-                // Diagonal lines
-                vec![0, 6, 12, 18, 24],
-                vec![4, 8, 12, 16, 20],
-            ],
-        );
-
-        for combination in winner_combinations.get(&self.size).unwrap() {
+        // TODO:Create these lines once when creating game, and pass immutable reference to this fn
+        for combination in self.winning_lines() {
             let mut player_x = 0;
             let mut player_o = 0;
-            for &index in combination {
+            for &index in &combination {
                 match self.fields[index] {
                     Field::Player(Player::X) => player_x += 1,
                     Field::Player(Player::O) => player_o += 1,
                     _ => continue,
                 }
             }
-            if player_x == self.size {
-                return GameResult::Player(Player::X);
+            if player_x == self.win_length && self.line_counts(&combination, Player::X) {
+                return self.line_completed_by(Player::X);
             }
-            if player_o == self.size {
-                return GameResult::Player(Player::O);
+            if player_o == self.win_length && self.line_counts(&combination, Player::O) {
+                return self.line_completed_by(Player::O);
             }
         }
-        let is_play = self.fields.iter().any(|&x| x == Field::Free);
+        // In Renju X can be left with nothing but forbidden fields
+        let is_play = self
+            .fields
+            .iter()
+            .enumerate()
+            .any(|(index, &x)| x == Field::Free && !(self.player_turn == Player::X && self.is_forbidden(index)));
         if is_play {
             return GameResult::InProgress;
         }
         return GameResult::Draw;
     }
 
+    // A full line of `player` only wins as part of a row of exactly win_length when
+    // overlines don't count
+    fn line_counts(&self, line: &[usize], player: Player) -> bool {
+        match (self.rules, player) {
+            (RuleSet::FreeStyle, _) | (RuleSet::Renju, Player::O) => true,
+            _ => DIRECTIONS
+                .iter()
+                .map(|&direction| self.run(line[0], direction))
+                .find(|run| line.iter().all(|field| run.contains(field)))
+                .is_some_and(|run| run.len() == self.win_length as usize),
+        }
+    }
+
+    fn line_completed_by(&self, player: Player) -> GameResult {
+        if self.misere {
+            GameResult::Player(player.opponent())
+        } else {
+            GameResult::Player(player)
+        }
+    }
+
     // Return numbers of lines that you can win on vs lines enemy can win on
+    // (in misere open lines are a liability, so the sign flips)
     fn lines_heuristic(&self, player: Player) -> i32 {
-        let mut winner_combinations = HashMap::new();
-        winner_combinations.insert(
-            2,
-            vec![
-                // Horizontal lines
-                vec![0, 1],
-                vec![2, 3],
-                // Vertical lines
-                vec![0, 2],
-                vec![1, 3],
-                // Diagonal lines
-                vec![0, 3],
-                vec![1, 2],
-            ],
-        );
-        winner_combinations.insert(
-            3,
-            vec![
-                // Horizontal lines
-                vec![0, 1, 2],
-                vec![3, 4, 5],
-                vec![6, 7, 8],
-                // Vertical lines
-                vec![0, 3, 6],
-                vec![1, 4, 7],
-                vec![2, 5, 8],
-                // Diagonal lines
-                vec![0, 4, 8],
-                vec![2, 4, 6],
-            ],
-        );
-        winner_combinations.insert(
-            4,
-            vec![
-                // Horizontal lines
-                vec![0, 1, 2, 3],
-                vec![4, 5, 6, 7],
-                vec![8, 9, 10, 11],
-                vec![12, 13, 14, 15],
-                // Vertical lines
-                vec![0, 4, 8, 12],
-                vec![1, 5, 9, 13],
-                vec![2, 6, 10, 14],
-                vec![3, 7, 11, 15],
-                // Diagonal lines
-                vec![0, 5, 10, 15],
-                vec![3, 6, 9, 12],
-            ],
-        );
-        winner_combinations.insert(
-            5,
-            vec![
-                // Horizontal lines
-                vec![0, 1, 2, 3, 4],
-                vec![5, 6, 7, 8, 9],
-                vec![10, 11, 12, 13, 14],
-                vec![15, 16, 17, 18, 19],
-                vec![20, 21, 22, 23, 24],
-                // Vertical lines
-                vec![0, 5, 10, 15, 20],
-                vec![1, 6, 11, 16, 21],
-                vec![2, 7, 12, 17, 22],
-                vec![3, 8, 13, 18, 23],
-                vec![4, 9, 14, 19, 24],
-                // Diagonal lines
-                vec![0, 6, 12, 18, 24],
-                vec![4, 8, 12, 16, 20],
-            ],
-        );
-        let mut p_possible_wins: i32 = self.size as i32 * 2 + 2;
-        let mut o_possible_wins: i32 = self.size as i32 * 2 + 2;
-
-        for combination in winner_combinations.get(&self.size).unwrap() {
+        let winner_combinations = self.winning_lines();
+        let mut p_possible_wins: i32 = winner_combinations.len() as i32;
+        let mut o_possible_wins: i32 = winner_combinations.len() as i32;
+
+        for combination in &winner_combinations {
             if combination
                 .iter()
                 .any(|&x| self.fields[x] == Field::Player(player))
@@ -314,10 +379,21 @@ impl Board {
             }
         }
 
+        if self.misere {
+            return o_possible_wins - p_possible_wins;
+        }
         p_possible_wins - o_possible_wins
     }
+    // The fields better_heuristic weighs are those of a square board without gravity
+    fn square_layout(&self) -> bool {
+        self.height == self.size && !self.gravity && !self.torus
+    }
+
     // TODO: There is bug to fix
     fn better_heuristic(&self, player: Player) -> i32 {
+        if !self.square_layout() {
+            return self.lines_heuristic(player);
+        }
         let mut result = 0;
 
         // let mut winner_combinations = HashMap::new();
@@ -435,15 +511,35 @@ impl Board {
             _ => {}
         }
 
+        if self.misere {
+            return -result;
+        }
         result
     }
 
+    // `index` is a column number on gravity boards
     fn make_move(&mut self, index: u32, len: u32) -> Result<(), &'static str> {
-        if index <= len {
+        let renju_x = self.rules == RuleSet::Renju && self.player_turn == Player::X;
+        if renju_x && index < len && self.is_forbidden(index as usize) {
+            return Err("Forbidden move!");
+        }
+        if self.gravity {
+            if index >= self.size {
+                return Err("Wrong column number");
+            }
+            let field = self.drop_field(index).ok_or("Column is full!")?;
+            self.fields[field] = Field::Player(self.player_turn);
+            self.moves.push(index);
+            self.turns.push(1);
+            self.player_turn = self.player_turn.opponent();
+            return Ok(());
+        }
+        if index < len {
             match self.fields[index as usize] {
                 Field::Free => {
                     self.fields[index as usize] = Field::Player(self.player_turn);
                     self.moves.push(index);
+                    self.turns.push(1);
                     self.player_turn = self.player_turn.opponent();
                     Ok(())
                 }
@@ -454,33 +550,207 @@ impl Board {
         }
     }
 
-    fn undo_last_move(&mut self) -> Result<(), &'static str> {
-        if self.moves.is_empty() {
-            return Err("No moves has been played");
+    // Places `stones` for the side to move as one turn, all of them or none
+    fn make_turn(&mut self, stones: &[u32], len: u32) -> Result<(), &'static str> {
+        if stones.is_empty() {
+            return Err("No stones to place");
         }
-        if let Some(last_move) = self.moves.pop() {
-            if self.fields[last_move as usize] == Field::Free {
-                panic!("Moves and board were not in sync!");
+        let player = self.player_turn;
+        for (placed, &index) in stones.iter().enumerate() {
+            self.player_turn = player;
+            if let Err(error) = self.make_move(index, len) {
+                for _ in 0..placed {
+                    self.undo_last_move()?;
+                }
+                self.player_turn = player;
+                return Err(error);
             }
-            self.fields[last_move as usize] = Field::Free;
-            self.player_turn = self.player_turn.opponent();
-            return Ok(());
         }
-        Err("Something went Wrong")
+        // make_move counted every stone as a turn of its own
+        self.turns.truncate(self.turns.len() - stones.len());
+        self.turns.push(stones.len());
+        self.player_turn = player.opponent();
+        Ok(())
     }
 
-    fn generate_moves(&self) -> Vec<Self> {
-        let mut result = vec![];
-        for (index, &field) in self.fields.iter().enumerate() {
+    // Takes back the whole last turn
+    fn undo_last_move(&mut self) -> Result<(), &'static str> {
+        let stones = *self.turns.last().ok_or("No moves has been played")?;
+        if stones > self.moves.len() {
+            return Err("Moves and turns were not in sync!");
+        }
+        let player = self.player_turn.opponent();
+        let mut freed = vec![];
+        for index in (self.moves.len() - stones..self.moves.len()).rev() {
+            let last_move = self.moves[index];
+            let field = if self.gravity {
+                self.top_field(last_move)
+            } else {
+                Some(last_move as usize)
+            };
             match field {
-                Field::Player(_) => {}
-                Field::Free => {
-                    let mut temp = self.clone();
-                    temp.make_move(index as u32, self.size * self.size).unwrap();
-                    result.push(temp);
+                Some(field) if self.fields[field] != Field::Free => {
+                    self.fields[field] = Field::Free;
+                    freed.push(field);
+                }
+                _ => {
+                    for field in freed {
+                        self.fields[field] = Field::Player(player);
+                    }
+                    return Err("Moves and board were not in sync!");
+                }
+            }
+        }
+        self.moves.truncate(self.moves.len() - stones);
+        self.turns.pop();
+        self.player_turn = player;
+        Ok(())
+    }
+
+    // Lowest free field of a column, None when the column is full
+    fn drop_field(&self, column: u32) -> Option<usize> {
+        (0..self.height)
+            .rev()
+            .map(|row| (row * self.size + column) as usize)
+            .find(|&field| self.fields[field] == Field::Free)
+    }
+
+    // Highest taken field of a column, None when the column is empty
+    fn top_field(&self, column: u32) -> Option<usize> {
+        (0..self.height)
+            .map(|row| (row * self.size + column) as usize)
+            .find(|&field| self.fields[field] != Field::Free)
+    }
+
+    // Everything make_move accepts: free fields, or columns that are not full
+    fn free_moves(&self) -> Vec<u32> {
+        if self.gravity {
+            return (0..self.size).filter(|&column| self.drop_field(column).is_some()).collect();
+        }
+        let renju_x = self.rules == RuleSet::Renju && self.player_turn == Player::X;
+        self.fields
+            .iter()
+            .enumerate()
+            .filter(|(index, value)| **value == Field::Free && !(renju_x && self.is_forbidden(*index)))
+            .map(|(index, _)| index as u32)
+            .collect()
+    }
+
+    // Field `distance` steps from `field` in `direction`, None past the edge
+    fn step(&self, field: usize, (dr, dc): (i32, i32), distance: i32) -> Option<usize> {
+        let n = self.size as i32;
+        let rows = self.height as i32;
+        let row = field as i32 / n + dr * distance;
+        let col = field as i32 % n + dc * distance;
+        if self.torus {
+            return Some((row.rem_euclid(rows) * n + col.rem_euclid(n)) as usize);
+        }
+        if (0..rows).contains(&row) && (0..n).contains(&col) {
+            Some((row * n + col) as usize)
+        } else {
+            None
+        }
+    }
+
+    // Unbroken row of the owner of `field` through it in `direction`
+    fn run(&self, field: usize, direction: (i32, i32)) -> Vec<usize> {
+        let mut result = vec![field];
+        for sign in [1, -1] {
+            let mut distance = sign;
+            while let Some(next) = self.step(field, direction, distance) {
+                if self.fields[next] != self.fields[field] || result.contains(&next) {
+                    break;
                 }
+                result.push(next);
+                distance += sign;
             }
         }
+        result
+    }
+
+    // Fours through the X stone on `field` in `direction`: the stones of every row that one
+    // more X makes exactly win_length long, with the number of fields that do it (two
+    // for a straight four)
+    fn fours(&mut self, field: usize, direction: (i32, i32)) -> Vec<(Vec<usize>, u32)> {
+        let k = self.win_length as i32;
+        let mut result: Vec<(Vec<usize>, u32)> = vec![];
+        for distance in (1 - k..k).filter(|&d| d != 0) {
+            let Some(completion) = self.step(field, direction, distance) else {
+                continue;
+            };
+            if self.fields[completion] != Field::Free {
+                continue;
+            }
+            self.fields[completion] = Field::Player(Player::X);
+            let run = self.run(field, direction);
+            self.fields[completion] = Field::Free;
+            if run.len() != k as usize || !run.contains(&completion) {
+                continue;
+            }
+            let mut stones: Vec<usize> = run.into_iter().filter(|&f| f != completion).collect();
+            stones.sort();
+            match result.iter_mut().find(|(s, _)| *s == stones) {
+                Some((_, count)) => *count += 1,
+                None => result.push((stones, 1)),
+            }
+        }
+        result
+    }
+
+    // Is there a three through the X stone on `field` in `direction`, a field that turns
+    // it into a straight four and isn't forbidden itself?
+    fn is_three(&mut self, field: usize, direction: (i32, i32)) -> bool {
+        let k = self.win_length as i32;
+        for distance in (1 - k..k).filter(|&d| d != 0) {
+            let Some(extra) = self.step(field, direction, distance) else {
+                continue;
+            };
+            if self.fields[extra] != Field::Free {
+                continue;
+            }
+            self.fields[extra] = Field::Player(Player::X);
+            let straight = self
+                .fours(field, direction)
+                .iter()
+                .any(|(stones, count)| *count == 2 && stones.contains(&extra));
+            self.fields[extra] = Field::Free;
+            if straight && !self.is_forbidden(extra) {
+                return true;
+            }
+        }
+        false
+    }
+
+    // Renju restrictions for X on a free field: an overline, two fours or two threes are
+    // forbidden, unless the move also makes exactly win_length in a row
+    fn is_forbidden(&self, field: usize) -> bool {
+        if self.rules != RuleSet::Renju || self.fields[field] != Field::Free {
+            return false;
+        }
+        let mut board = self.clone();
+        board.fields[field] = Field::Player(Player::X);
+        let k = self.win_length as usize;
+        let runs: Vec<usize> = DIRECTIONS.iter().map(|&d| board.run(field, d).len()).collect();
+        if runs.contains(&k) {
+            return false;
+        }
+        if runs.iter().any(|&length| length > k) {
+            return true;
+        }
+        let fours: usize = DIRECTIONS.iter().map(|&d| board.fours(field, d).len()).sum();
+        if fours >= 2 {
+            return true;
+        }
+        DIRECTIONS.iter().filter(|&&d| board.is_three(field, d)).count() >= 2
+    }
+
+    fn generate_moves(&self) -> Vec<Self> {
+        let mut result = vec![];
+        for index in self.free_moves() {
+            let mut temp = self.clone();
+            temp.make_move(index, self.size * self.size).unwrap();
+            result.push(temp);
+        }
         return result;
     }
     fn generate_sorted_lines_heuristic(&self) -> Vec<Self> {
@@ -527,41 +797,153 @@ impl Board {
         }
     }
     fn find_best_move_alfa_beta(&self) -> u32 {
+        self.find_best_move_lookup(&mut HashMap::new())
+    }
+
+    // find_best_move_alfa_beta with a caller owned table, so it stays warm between moves
+    fn find_best_move_lookup(&self, lookup: &mut HashMap<Board, GameResult>) -> u32 {
+        self.find_best_move_lookup_until(lookup, &NO_STOP).unwrap()
+    }
+
+    // find_best_move_lookup that gives up without a move once `stop` is set
+    fn find_best_move_lookup_until(&self, lookup: &mut HashMap<Board, GameResult>, stop: &AtomicBool) -> Option<u32> {
         let alfa = GameResult::Player(Player::O);
         let beta = GameResult::Player(Player::X);
-        let mut lookup = HashMap::new();
+        let mut search = |board: &Board| {
+            pre_alpha_beta_look(&mut board.clone(), alfa, beta, board.player_turn, gen_linear_heuristic, lookup, stop)
+        };
         match self.player_turn {
             Player::X => {
                 let mut best_move = self.generate_moves()[0].clone(); // I'll get int at the end!
-                let mut best_move_score = alpha_beta_lookup_h1(&mut best_move, 10, alfa, beta, Player::O, &mut lookup);
+                let mut best_move_score = search(&best_move);
                 for legal_board in self.generate_moves() {
-                    if alpha_beta_lookup_h1(&mut legal_board.clone(), 10, alfa, beta, Player::O, &mut lookup).result
-                        > best_move_score.result
-                    {
-                        best_move_score =
-                            alpha_beta_lookup_h1(&mut legal_board.clone(), 10, alfa, beta, Player::O, &mut lookup);
+                    if search(&legal_board).result > best_move_score.result {
+                        best_move_score = search(&legal_board);
                         best_move = legal_board;
                     }
                 }
-                return *best_move.moves.last().unwrap();
+                if stop.load(atomic::Ordering::Relaxed) {
+                    return None;
+                }
+                return Some(*best_move.moves.last().unwrap());
             }
             Player::O => {
                 // Do the same but just flip grater sign i guess ?
                 let mut best_move = self.generate_moves()[0].clone();
-                let mut best_move_score = alpha_beta_lookup_h1(&mut best_move, 10, alfa, beta, Player::X, &mut lookup);
+                let mut best_move_score = search(&best_move);
                 for legal_board in self.generate_moves() {
-                    if alpha_beta_lookup_h1(&mut legal_board.clone(), 10, alfa, beta, Player::X, &mut lookup).result
-                        < best_move_score.result
-                    {
-                        best_move_score =
-                            alpha_beta_lookup_h1(&mut legal_board.clone(), 10, alfa, beta, Player::X, &mut lookup);
+                    if search(&legal_board).result < best_move_score.result {
+                        best_move_score = search(&legal_board);
                         best_move = legal_board;
                     }
                 }
-                return *best_move.moves.last().unwrap();
+                if stop.load(atomic::Ordering::Relaxed) {
+                    return None;
+                }
+                return Some(*best_move.moves.last().unwrap());
+            }
+        }
+    }
+    // Same result as find_best_move_alfa_beta, but among the equally good moves it picks
+    // the one with the largest fraction of opponent replies that make their result worse.
+    fn find_best_move_exploit(&self) -> u32 {
+        let alfa = GameResult::Player(Player::O);
+        let beta = GameResult::Player(Player::X);
+        let mut lookup = HashMap::new();
+        let mut value = |board: &Board| {
+            alpha_beta_lookup_h1(&mut board.clone(), 10, alfa, beta, board.player_turn, &mut lookup).result
+        };
+        let better = |a: GameResult, b: GameResult| match self.player_turn {
+            Player::X => a > b,
+            Player::O => a < b,
+        };
+
+        let candidates: Vec<(Board, GameResult)> = self
+            .generate_moves()
+            .into_iter()
+            .map(|b| {
+                let result = value(&b);
+                (b, result)
+            })
+            .collect();
+        let mut best_result = candidates[0].1;
+        for &(_, result) in &candidates {
+            if better(result, best_result) {
+                best_result = result;
             }
         }
+
+        let mut best_move = 0;
+        let mut best_fraction = -1.0;
+        for (board, result) in &candidates {
+            if better(best_result, *result) {
+                continue;
+            }
+            let replies = board.generate_moves();
+            let fraction = if replies.is_empty() {
+                1.0
+            } else {
+                let mistakes = replies
+                    .iter()
+                    .filter(|reply| better(value(reply), best_result))
+                    .count();
+                mistakes as f64 / replies.len() as f64
+            };
+            if fraction > best_fraction {
+                best_fraction = fraction;
+                best_move = *board.moves.last().unwrap();
+            }
+        }
+        best_move
+    }
+
+    fn find_best_move_depth(&self, depth: i8, eval: &dyn Fn(&Board) -> i32) -> u32 {
+        best_move_depth(self, depth, eval)
     }
+    // MAX_LEVEL plays perfectly, breaking ties with `tie_break`. Every level below it
+    // searches fewer plies, plays a random move more often and picks among the scored
+    // moves with a softmax that gets hotter as the level drops.
+    fn find_move_at_level(&self, level: u8, tie_break: TieBreak) -> u32 {
+        let level = level.clamp(1, MAX_LEVEL);
+        if level == MAX_LEVEL {
+            return match tie_break {
+                TieBreak::First => self.find_best_move_alfa_beta(),
+                TieBreak::Exploit => self.find_best_move_exploit(),
+            };
+        }
+        let weakness = (MAX_LEVEL - level) as f64;
+        let mut rng = rand::thread_rng();
+        let moves = self.generate_moves();
+        if rng.gen::<f64>() < weakness * 0.07 {
+            return *moves[rng.gen_range(0..moves.len())].moves.last().unwrap();
+        }
+
+        let scores: Vec<f64> = moves
+            .iter()
+            .map(|b| {
+                let score =
+                    alpha_beta_depth(&mut b.clone(), level as i8 - 1, i32::MIN, i32::MAX, &lines_eval);
+                // A forced win is worth a lot of lines, but must not drown the temperature
+                let score = score.clamp(-100, 100) as f64;
+                match self.player_turn {
+                    Player::X => score,
+                    Player::O => -score,
+                }
+            })
+            .collect();
+        let temperature = weakness * 0.5;
+        let max = scores.iter().cloned().fold(f64::MIN, f64::max);
+        let weights: Vec<f64> = scores.iter().map(|s| ((s - max) / temperature).exp()).collect();
+        let mut pick = rng.gen::<f64>() * weights.iter().sum::<f64>();
+        for (index, weight) in weights.iter().enumerate() {
+            if pick < *weight {
+                return *moves[index].moves.last().unwrap();
+            }
+            pick -= weight;
+        }
+        *moves.last().unwrap().moves.last().unwrap()
+    }
+
     fn rot90board(&self) -> Self {
         let mut result_board = vec![Field::Free; (self.size * self.size) as usize];
 
@@ -575,14 +957,71 @@ impl Board {
         Board {
             fields: result_board,
             moves: self.moves.clone(),
+            turns: self.turns.clone(),
             player_turn: self.player_turn,
             size: self.size,
+            height: self.height,
+            win_length: self.win_length,
+            misere: self.misere,
+            gravity: self.gravity,
+            torus: self.torus,
+            rules: self.rules,
+        }
+    }
+
+    // Left/right mirror image, the only symmetry that keeps the direction of gravity
+    fn mirror_board(&self) -> Self {
+        let mut fields = self.fields.clone();
+        for row in 0..self.height {
+            for column in 0..self.size {
+                fields[(row * self.size + column) as usize] =
+                    self.fields[(row * self.size + self.size - 1 - column) as usize];
+            }
+        }
+        let moves = if self.gravity {
+            self.moves.iter().map(|&column| self.size - 1 - column).collect()
+        } else {
+            self.moves.iter().map(|&m| m - m % self.size + self.size - 1 - m % self.size).collect()
+        };
+        Self {
+            fields,
+            moves,
+            ..self.clone()
         }
     }
 }
 
+// Known shapes around the center, X playing the center: (name, X stones, O stones, forbidden)
+type Shape = (&'static str, &'static [(i32, i32)], &'static [(i32, i32)], bool);
+
+const RENJU_SHAPES: [Shape; 7] = [
+    ("double three", &[(0, -2), (0, -1), (-2, 0), (-1, 0)], &[], true),
+    ("split double three", &[(0, -3), (0, -1), (-2, 0), (-1, 0)], &[], true),
+    ("double four", &[(0, -3), (0, -2), (0, -1), (-3, 0), (-2, 0), (-1, 0)], &[], true),
+    ("overline", &[(0, -4), (0, -3), (0, -2), (0, -1), (0, 1)], &[], true),
+    ("four-three", &[(0, -3), (0, -2), (0, -1), (-2, 0), (-1, 0)], &[], false),
+    ("five", &[(0, -4), (0, -3), (0, -2), (0, -1)], &[], false),
+    ("blocked three", &[(0, -2), (0, -1), (-2, 0), (-1, 0)], &[(0, -3), (0, 2)], false),
+];
+
+// Renju board with the stones of a shape around the center, and the center
+fn renju_shape(size: u32, x_stones: &[(i32, i32)], o_stones: &[(i32, i32)]) -> (Board, usize) {
+    let center = (size / 2 * size + size / 2) as usize;
+    let mut board = Board::create_gomoku_board(size, RuleSet::Renju);
+    for (stones, player) in [(x_stones, Player::X), (o_stones, Player::O)] {
+        for &(dr, dc) in stones {
+            let field = board.step(center, (dr, dc), 1).unwrap();
+            board.fields[field] = Field::Player(player);
+        }
+    }
+    (board, center)
+}
+
 impl fmt::Debug for Board {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.torus {
+            return self.fmt_torus(f);
+        }
         for (index, field) in self.fields.iter().enumerate() {
             if index % self.size as usize == 0 {
                 write!(f, "\n")?;
@@ -601,23 +1040,66 @@ impl fmt::Debug for Board {
             write!(f, "-----")?;
         }
         write!(f, "\n")?;
+        // Moves are column numbers, so show them under the columns
+        if self.gravity {
+            for column in 0..self.size {
+                write!(f, " {:<4}", column)?;
+            }
+            writeln!(f)?;
+        }
+        if self.rules == RuleSet::Renju && self.player_turn == Player::X {
+            let forbidden: Vec<usize> = (0..self.fields.len()).filter(|&i| self.is_forbidden(i)).collect();
+            if !forbidden.is_empty() {
+                writeln!(f, "Forbidden for X: {:?}", forbidden)?;
+            }
+        }
         Ok(())
     }
 }
 
-fn pre_alpha_beta(
-    board: &mut Board,
+impl Board {
+    // The board framed by the rows and columns it wraps around to, in lower case, so
+    // lines crossing an edge can be read off directly
+    fn fmt_torus(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let n = self.size as i32;
+        let rows = self.height as i32;
+        let cell = |row: i32, col: i32| {
+            let field = self.fields[(row.rem_euclid(rows) * n + col.rem_euclid(n)) as usize];
+            let text = format!("{:?}", field);
+            if (0..rows).contains(&row) && (0..n).contains(&col) {
+                text
+            } else if field == Field::Free {
+                ".".to_string()
+            } else {
+                text.to_lowercase()
+            }
+        };
+        let rule = format!("     {}", "-".repeat(5 * n as usize - 2));
+        for row in -1..=rows {
+            if row == 0 || row == rows {
+                writeln!(f, "{}", rule)?;
+            }
+            let cells: Vec<String> = (0..n).map(|col| format!(" {} ", cell(row, col))).collect();
+            let separator = if (0..rows).contains(&row) { " |" } else { "  " };
+            writeln!(f, " {} : {} : {}", cell(row, -1), cells.join(separator), cell(row, n))?;
+        }
+        writeln!(f, "Torus: the edges wrap around")
+    }
+}
+
+fn pre_alpha_beta<G: GameRules>(
+    board: &mut G,
     _depth: i8,
     mut alfa: GameResult,
     mut beta: GameResult,
     max_player: Player,
-    gen_nodes: fn(&Board) -> Vec<Board>
+    gen_nodes: fn(&G) -> Vec<G::Move>
 ) -> SearchStats {
     let start_time = Instant::now();
     unsafe {
         COUNTER += 1;
     }
-    let result = board.get_result();
+    let result = board.result();
     match result {
         GameResult::Draw => SearchStats {
             result: result,
@@ -637,11 +1119,9 @@ fn pre_alpha_beta(
                     let possible_moves = gen_nodes(board);
                     //println!("{:?}", possible_moves.len());
                     for legal_move in possible_moves {
-                        board
-                            .make_move(*legal_move.moves.last().unwrap(), board.size * board.size)
-                            .unwrap();
+                        board.apply(legal_move).unwrap();
                         let local_result =
-                            pre_alpha_beta(board, _depth - 1, alfa, beta, board.player_turn, gen_nodes);
+                            pre_alpha_beta(board, _depth - 1, alfa, beta, board.side_to_move(), gen_nodes);
                         if local_result.result > best_score {
                             best_score = local_result.result;
                         }
@@ -649,10 +1129,10 @@ fn pre_alpha_beta(
                             alfa = best_score;
                         }
                         if best_score >= beta {
-                            board.undo_last_move().unwrap();
+                            board.undo().unwrap();
                             break;
                         }
-                        board.undo_last_move().unwrap();
+                        board.undo().unwrap();
                     }
                     return SearchStats {
                         result: best_score,
@@ -662,19 +1142,11 @@ fn pre_alpha_beta(
                 }
                 Player::O => {
                     let mut best_score = GameResult::Player(Player::X);
-                    let possible_moves: Vec<usize> = board
-                        .fields
-                        .iter()
-                        .enumerate()
-                        .filter(|(_, value)| **value == Field::Free)
-                        .map(|(index, _)| index)
-                        .collect();
+                    let possible_moves = board.legal_moves();
                     for legal_move in possible_moves {
-                        board
-                            .make_move(legal_move as u32, board.size * board.size)
-                            .unwrap();
+                        board.apply(legal_move).unwrap();
                         let local_result =
-                            pre_alpha_beta(board, _depth - 1, alfa, beta, board.player_turn, gen_nodes);
+                            pre_alpha_beta(board, _depth - 1, alfa, beta, board.side_to_move(), gen_nodes);
                         if local_result.result < best_score {
                             best_score = local_result.result;
                         }
@@ -682,10 +1154,10 @@ fn pre_alpha_beta(
                             beta = best_score;
                         }
                         if best_score <= alfa {
-                            board.undo_last_move().unwrap();
+                            board.undo().unwrap();
                             break;
                         }
-                        board.undo_last_move().unwrap();
+                        board.undo().unwrap();
                     }
                     return SearchStats {
                         result: best_score,
@@ -698,14 +1170,21 @@ fn pre_alpha_beta(
     }
 }
 
-fn pre_alpha_beta_look(
-    board: &mut Board,
-    _depth: i8,
+// A score inside the search window is exact, outside it only a bound on the real
+// result (unless it is a win, nothing lies beyond those). Only exact scores may be
+// stored in a lookup that later searches with other windows will read.
+fn exact_score(score: GameResult, (alfa, beta): (GameResult, GameResult)) -> bool {
+    (alfa < score && score < beta) || matches!(score, GameResult::Player(_))
+}
+
+fn pre_alpha_beta_look<G: GameRules>(
+    board: &mut G,
     mut alfa: GameResult,
     mut beta: GameResult,
     max_player: Player,
-    gen_nodes: fn(&Board) -> Vec<Board>,
-    look_up: &mut HashMap<Board, GameResult>,
+    gen_nodes: fn(&G) -> Vec<G::Move>,
+    look_up: &mut HashMap<G, GameResult>,
+    stop: &AtomicBool,
 ) -> SearchStats {
     let start_time = Instant::now();
     match look_up.get(board) {
@@ -714,11 +1193,17 @@ fn pre_alpha_beta_look(
             visited: 10,
             time: start_time.elapsed(),
         },
+        // Interrupted: the result means nothing, every caller gives up as well
+        _ if stop.load(atomic::Ordering::Relaxed) => SearchStats {
+            result: GameResult::InProgress,
+            visited: 10,
+            time: start_time.elapsed(),
+        },
         _ => {
             unsafe {
                 COUNTER += 1;
             }
-            let result = board.get_result();
+            let result = board.result();
             match result {
                 GameResult::Draw => {
                     look_up.insert(board.clone(), result);
@@ -737,6 +1222,7 @@ fn pre_alpha_beta_look(
                     }
                 }
                 GameResult::InProgress => {
+                    let window = (alfa, beta);
                     match max_player {
                         Player::X => {
                             // We want to maximize the score
@@ -744,11 +1230,19 @@ fn pre_alpha_beta_look(
                             let possible_moves = gen_nodes(board);
                             //println!("{:?}", possible_moves.len());
                             for legal_move in possible_moves {
-                                board
-                                    .make_move(*legal_move.moves.last().unwrap(), board.size * board.size)
-                                    .unwrap();
+                                board.apply(legal_move).unwrap();
                                 let local_result =
-                                    pre_alpha_beta_look(board, _depth - 1, alfa, beta, board.player_turn, gen_nodes, look_up);
+                                    pre_alpha_beta_look(board, alfa, beta, board.side_to_move(), gen_nodes, look_up, stop);
+                                if stop.load(atomic::Ordering::Relaxed) {
+                                    // Unfinished, so nothing goes into the table
+                                    board.undo().unwrap();
+                                    return local_result;
+                                }
+                                if stop.load(atomic::Ordering::Relaxed) {
+                                    // Unfinished, so nothing goes into the table
+                                    board.undo().unwrap();
+                                    return local_result;
+                                }
                                 if local_result.result > best_score {
                                     best_score = local_result.result;
                                 }
@@ -756,12 +1250,14 @@ fn pre_alpha_beta_look(
                                     alfa = best_score;
                                 }
                                 if best_score >= beta {
-                                    board.undo_last_move().unwrap();
+                                    board.undo().unwrap();
                                     break;
                                 }
-                                board.undo_last_move().unwrap();
+                                board.undo().unwrap();
+                            }
+                            if exact_score(best_score, window) {
+                                look_up.insert(board.clone(), best_score);
                             }
-                            look_up.insert(board.clone(), best_score);
                             return SearchStats {
                                 result: best_score,
                                 visited: 10,
@@ -770,19 +1266,16 @@ fn pre_alpha_beta_look(
                         }
                         Player::O => {
                             let mut best_score = GameResult::Player(Player::X);
-                            let possible_moves: Vec<usize> = board
-                                .fields
-                                .iter()
-                                .enumerate()
-                                .filter(|(_, value)| **value == Field::Free)
-                                .map(|(index, _)| index)
-                                .collect();
+                            let possible_moves = board.legal_moves();
                             for legal_move in possible_moves {
-                                board
-                                    .make_move(legal_move as u32, board.size * board.size)
-                                    .unwrap();
+                                board.apply(legal_move).unwrap();
                                 let local_result =
-                                   pre_alpha_beta_look(board, _depth - 1, alfa, beta, board.player_turn, gen_nodes, look_up);
+                                   pre_alpha_beta_look(board, alfa, beta, board.side_to_move(), gen_nodes, look_up, stop);
+                                if stop.load(atomic::Ordering::Relaxed) {
+                                    // Unfinished, so nothing goes into the table
+                                    board.undo().unwrap();
+                                    return local_result;
+                                }
                                 if local_result.result < best_score {
                                     best_score = local_result.result;
                                 }
@@ -790,12 +1283,14 @@ fn pre_alpha_beta_look(
                                     beta = best_score;
                                 }
                                 if best_score <= alfa {
-                                    board.undo_last_move().unwrap();
+                                    board.undo().unwrap();
                                     break;
                                 }
-                                board.undo_last_move().unwrap();
+                                board.undo().unwrap();
+                            }
+                            if exact_score(best_score, window) {
+                                look_up.insert(board.clone(), best_score);
                             }
-                            look_up.insert(board.clone(), best_score);
                             return SearchStats {
                                 result: best_score,
                                 visited: 10,
@@ -809,17 +1304,17 @@ fn pre_alpha_beta_look(
     }
 }
 
-fn pre_alpha_beta_look_sym(
-    board: &mut Board,
+fn pre_alpha_beta_look_sym<G: GameRules>(
+    board: &mut G,
     _depth: i8,
     mut alfa: GameResult,
     mut beta: GameResult,
     max_player: Player,
-    gen_nodes: fn(&Board) -> Vec<Board>,
-    look_up: &mut HashMap<Board, GameResult>,
+    gen_nodes: fn(&G) -> Vec<G::Move>,
+    look_up: &mut HashMap<G, GameResult>,
 ) -> SearchStats {
     let start_time = Instant::now();
-    let lookup_result = check_for_rotation(&mut board.clone(), look_up);
+    let lookup_result = check_for_symmetry(board, look_up);
     match lookup_result {
         Some(res) => SearchStats {
             result: res,
@@ -830,7 +1325,7 @@ fn pre_alpha_beta_look_sym(
             unsafe {
                 COUNTER += 1;
             }
-            let result = board.get_result();
+            let result = board.result();
             match result {
                 GameResult::Draw => {
                     look_up.insert(board.clone(), result);
@@ -849,6 +1344,7 @@ fn pre_alpha_beta_look_sym(
                     }
                 }
                 GameResult::InProgress => {
+                    let window = (alfa, beta);
                     match max_player {
                         Player::X => {
                             // We want to maximize the score
@@ -856,11 +1352,9 @@ fn pre_alpha_beta_look_sym(
                             let possible_moves = gen_nodes(board);
                             //println!("{:?}", possible_moves.len());
                             for legal_move in possible_moves {
-                                board
-                                    .make_move(*legal_move.moves.last().unwrap(), board.size * board.size)
-                                    .unwrap();
+                                board.apply(legal_move).unwrap();
                                 let local_result =
-                                    pre_alpha_beta_look_sym(board, _depth - 1, alfa, beta, board.player_turn, gen_nodes, look_up);
+                                    pre_alpha_beta_look_sym(board, _depth - 1, alfa, beta, board.side_to_move(), gen_nodes, look_up);
                                 if local_result.result > best_score {
                                     best_score = local_result.result;
                                 }
@@ -868,12 +1362,14 @@ fn pre_alpha_beta_look_sym(
                                     alfa = best_score;
                                 }
                                 if best_score >= beta {
-                                    board.undo_last_move().unwrap();
+                                    board.undo().unwrap();
                                     break;
                                 }
-                                board.undo_last_move().unwrap();
+                                board.undo().unwrap();
+                            }
+                            if exact_score(best_score, window) {
+                                look_up.insert(board.clone(), best_score);
                             }
-                            look_up.insert(board.clone(), best_score);
                             return SearchStats {
                                 result: best_score,
                                 visited: 10,
@@ -882,19 +1378,11 @@ fn pre_alpha_beta_look_sym(
                         }
                         Player::O => {
                             let mut best_score = GameResult::Player(Player::X);
-                            let possible_moves: Vec<usize> = board
-                                .fields
-                                .iter()
-                                .enumerate()
-                                .filter(|(_, value)| **value == Field::Free)
-                                .map(|(index, _)| index)
-                                .collect();
+                            let possible_moves = board.legal_moves();
                             for legal_move in possible_moves {
-                                board
-                                    .make_move(legal_move as u32, board.size * board.size)
-                                    .unwrap();
+                                board.apply(legal_move).unwrap();
                                 let local_result =
-                                    pre_alpha_beta_look_sym(board, _depth - 1, alfa, beta, board.player_turn, gen_nodes, look_up);
+                                    pre_alpha_beta_look_sym(board, _depth - 1, alfa, beta, board.side_to_move(), gen_nodes, look_up);
                                 if local_result.result < best_score {
                                     best_score = local_result.result;
                                 }
@@ -902,12 +1390,14 @@ fn pre_alpha_beta_look_sym(
                                     beta = best_score;
                                 }
                                 if best_score <= alfa {
-                                    board.undo_last_move().unwrap();
+                                    board.undo().unwrap();
                                     break;
                                 }
-                                board.undo_last_move().unwrap();
+                                board.undo().unwrap();
+                            }
+                            if exact_score(best_score, window) {
+                                look_up.insert(board.clone(), best_score);
                             }
-                            look_up.insert(board.clone(), best_score);
                             return SearchStats {
                                 result: best_score,
                                 visited: 10,
@@ -921,17 +1411,21 @@ fn pre_alpha_beta_look_sym(
     }
 }
 
-fn gen_non_heuristic(board: &Board) -> Vec<Board> {
-    board.generate_moves()
+fn gen_non_heuristic<G: GameRules>(board: &G) -> Vec<G::Move> {
+    board.legal_moves()
 }
-fn gen_linear_heuristic(board: &Board) -> Vec<Board> {
-    board.generate_sorted_lines_heuristic()
+fn gen_linear_heuristic(board: &Board) -> Vec<u32> {
+    board.ordered_moves()
 }
-fn gen_my_heuristic(board: &Board) -> Vec<Board> {
-    board.generate_own_heuristic()
+fn gen_my_heuristic(board: &Board) -> Vec<u32> {
+    board
+        .generate_own_heuristic()
+        .iter()
+        .map(|b| *b.moves.last().unwrap())
+        .collect()
 }
 
-fn alpha_beta(board: &mut Board,
+fn alpha_beta<G: GameRules>(board: &mut G,
              _depth: i8,
              mut alfa: GameResult,
              mut beta: GameResult,
@@ -960,14 +1454,14 @@ fn alpha_beta_h2(
     pre_alpha_beta(board, _depth, alfa, beta, max_player, gen_my_heuristic)
 }
 
-fn alpha_beta_lookup(board: &mut Board,
+fn alpha_beta_lookup<G: GameRules>(board: &mut G,
                      _depth: i8,
                      mut alfa: GameResult,
                      mut beta: GameResult,
                      max_player: Player,
-                     look_up: &mut HashMap<Board, GameResult>,
+                     look_up: &mut HashMap<G, GameResult>,
 ) -> SearchStats {
-    pre_alpha_beta_look(board, _depth, alfa, beta, max_player, gen_non_heuristic, look_up)
+    pre_alpha_beta_look(board, alfa, beta, max_player, gen_non_heuristic, look_up, &NO_STOP)
 }
 
 fn alpha_beta_lookup_h1(board: &mut Board,
@@ -977,7 +1471,7 @@ fn alpha_beta_lookup_h1(board: &mut Board,
                      max_player: Player,
                      look_up: &mut HashMap<Board, GameResult>,
 ) -> SearchStats {
-    pre_alpha_beta_look(board, _depth, alfa, beta, max_player, gen_linear_heuristic, look_up)
+    pre_alpha_beta_look(board, alfa, beta, max_player, gen_linear_heuristic, look_up, &NO_STOP)
 }
 
 fn alpha_beta_lookup_h2(board: &mut Board,
@@ -987,16 +1481,16 @@ fn alpha_beta_lookup_h2(board: &mut Board,
                      max_player: Player,
                      look_up: &mut HashMap<Board, GameResult>,
 ) -> SearchStats {
-    pre_alpha_beta_look(board, _depth, alfa, beta, max_player, gen_my_heuristic, look_up)
+    pre_alpha_beta_look(board, alfa, beta, max_player, gen_my_heuristic, look_up, &NO_STOP)
 }
 
-fn alpha_beta_lookup_sym(
-    board: &mut Board,
+fn alpha_beta_lookup_sym<G: GameRules>(
+    board: &mut G,
     _depth: i8,
     mut alfa: GameResult,
     mut beta: GameResult,
     max_player: Player,
-    look_up: &mut HashMap<Board, GameResult>,
+    look_up: &mut HashMap<G, GameResult>,
 ) -> SearchStats {
     pre_alpha_beta_look_sym(board, _depth, alfa, beta, max_player, gen_non_heuristic, look_up)
 }
@@ -1023,43 +1517,147 @@ fn alpha_beta_lookup_sym_h2(
     pre_alpha_beta_look_sym(board, _depth, alfa, beta, max_player, gen_my_heuristic, look_up)
 }
 
-fn check_for_rotation(
-    board: &mut Board,
-    lookup: &HashMap<Board, GameResult>,
-) -> Option<GameResult> {
-    match lookup.get(board) {
-        Some(res) => {
-            return Some(*res);
+const WIN_SCORE: i32 = 1_000_000;
+const MAX_LEVEL: u8 = 10;
+
+fn lines_eval(board: &Board) -> i32 {
+    board.lines_heuristic(Player::X)
+}
+
+// Alpha-beta on numeric scores instead of GameResult, X maximizes. Search stops after
+// `depth` plies and the position is scored by `eval` (lines_eval, NTuple::eval, ...).
+// Remaining depth is added to wins so quicker wins are preferred.
+fn alpha_beta_depth<G: GameRules>(
+    board: &mut G,
+    depth: i8,
+    mut alfa: i32,
+    mut beta: i32,
+    eval: &dyn Fn(&G) -> i32,
+) -> i32 {
+    unsafe {
+        COUNTER += 1;
+    }
+    match board.result() {
+        GameResult::Player(Player::X) => return WIN_SCORE + depth as i32,
+        GameResult::Player(Player::O) => return -WIN_SCORE - depth as i32,
+        GameResult::Draw => return 0,
+        GameResult::InProgress => {}
+    }
+    if depth <= 0 {
+        return eval(board);
+    }
+    let possible_moves = board.ordered_moves();
+    match board.side_to_move() {
+        Player::X => {
+            let mut best_score = i32::MIN;
+            for legal_move in possible_moves {
+                board.apply(legal_move).unwrap();
+                let score = alpha_beta_depth(board, depth - 1, alfa, beta, eval);
+                board.undo().unwrap();
+                best_score = best_score.max(score);
+                alfa = alfa.max(best_score);
+                if best_score >= beta {
+                    break;
+                }
+            }
+            best_score
+        }
+        Player::O => {
+            let mut best_score = i32::MAX;
+            for legal_move in possible_moves {
+                board.apply(legal_move).unwrap();
+                let score = alpha_beta_depth(board, depth - 1, alfa, beta, eval);
+                board.undo().unwrap();
+                best_score = best_score.min(score);
+                beta = beta.min(best_score);
+                if best_score <= alfa {
+                    break;
+                }
+            }
+            best_score
         }
-        _ => {}
     }
-    match lookup.get(&board.rot90board()) {
-        Some(res) => {
-            return Some(*res);
+}
+
+// Move with the best alpha_beta_depth score for the side to move
+fn best_move_depth<G: GameRules>(board: &G, depth: i8, eval: &dyn Fn(&G) -> i32) -> G::Move {
+    let mut board = board.clone();
+    let mut best_move = None;
+    let mut best_score = 0;
+    for legal_move in board.ordered_moves() {
+        board.apply(legal_move).unwrap();
+        let score = alpha_beta_depth(&mut board, depth - 1, i32::MIN, i32::MAX, eval);
+        board.undo().unwrap();
+        let better = match board.side_to_move() {
+            Player::X => score > best_score,
+            Player::O => score < best_score,
+        };
+        if best_move.is_none() || better {
+            best_score = score;
+            best_move = Some(legal_move);
         }
-        _ => {}
     }
-    match lookup.get(&board.rot90board().rot90board()) {
-        Some(res) => {
-            return Some(*res);
+    best_move.unwrap()
+}
+
+// Move with the best exact result, searched to the end with alpha_beta_lookup
+fn best_move_alpha_beta<G: GameRules>(board: &G, lookup: &mut HashMap<G, GameResult>) -> G::Move {
+    let alfa = GameResult::Player(Player::O);
+    let beta = GameResult::Player(Player::X);
+    let mut board = board.clone();
+    let player = board.side_to_move();
+    let mut best: Option<(G::Move, GameResult)> = None;
+    for legal_move in board.legal_moves() {
+        board.apply(legal_move).unwrap();
+        let result = alpha_beta_lookup(&mut board, 10, alfa, beta, player.opponent(), lookup).result;
+        board.undo().unwrap();
+        let better = match (best, player) {
+            (None, _) => true,
+            (Some((_, b)), Player::X) => result > b,
+            (Some((_, b)), Player::O) => result < b,
+        };
+        if better {
+            best = Some((legal_move, result));
         }
-        _ => {}
     }
-    match lookup.get(&board.rot90board().rot90board().rot90board()) {
-        Some(res) => {
+    best.unwrap().0
+}
+
+// Plays `board` to the end with one engine per side, returns the final position
+fn play_out<G: GameRules>(
+    mut board: G,
+    x_engine: &dyn Fn(&G) -> G::Move,
+    o_engine: &dyn Fn(&G) -> G::Move,
+) -> G {
+    while board.result() == GameResult::InProgress {
+        let m = match board.side_to_move() {
+            Player::X => x_engine(&board),
+            Player::O => o_engine(&board),
+        };
+        board.apply(m).unwrap();
+    }
+    board
+}
+
+// Looks the board up, then every board symmetric to it
+fn check_for_symmetry<G: GameRules>(board: &G, lookup: &HashMap<G, GameResult>) -> Option<GameResult> {
+    if let Some(res) = lookup.get(board) {
+        return Some(*res);
+    }
+    for symmetric in board.symmetries() {
+        if let Some(res) = lookup.get(&symmetric) {
             return Some(*res);
         }
-        _ => {}
     }
     None
 }
 
-fn min_max(board: &mut Board, _depth: i16, max_player: Player) -> SearchStats {
+fn min_max<G: GameRules>(board: &mut G, _depth: i16, max_player: Player) -> SearchStats {
     let start_time = Instant::now();
     unsafe {
         COUNTER += 1;
     }
-    let result = board.get_result();
+    let result = board.result();
     match result {
         GameResult::Draw => SearchStats {
             result: result,
@@ -1076,23 +1674,15 @@ fn min_max(board: &mut Board, _depth: i16, max_player: Player) -> SearchStats {
                 Player::X => {
                     // We want to maximize the score
                     let mut best_score = GameResult::Player(Player::O);
-                    let possible_moves: Vec<usize> = board
-                        .fields
-                        .iter()
-                        .enumerate()
-                        .filter(|(_, value)| **value == Field::Free)
-                        .map(|(index, _)| index)
-                        .collect();
+                    let possible_moves = board.legal_moves();
                     //println!("{:?}", possible_moves.len());
                     for legal_move in possible_moves {
-                        board
-                            .make_move(legal_move as u32, board.size * board.size)
-                            .unwrap();
-                        let local_result = min_max(board, _depth - 1, board.player_turn);
+                        board.apply(legal_move).unwrap();
+                        let local_result = min_max(board, _depth - 1, board.side_to_move());
                         if local_result.result > best_score {
                             best_score = local_result.result;
                         }
-                        board.undo_last_move().unwrap();
+                        board.undo().unwrap();
                     }
                     return SearchStats {
                         result: best_score,
@@ -1102,22 +1692,14 @@ fn min_max(board: &mut Board, _depth: i16, max_player: Player) -> SearchStats {
                 }
                 Player::O => {
                     let mut best_score = GameResult::Player(Player::X);
-                    let possible_moves: Vec<usize> = board
-                        .fields
-                        .iter()
-                        .enumerate()
-                        .filter(|(_, value)| **value == Field::Free)
-                        .map(|(index, _)| index)
-                        .collect();
+                    let possible_moves = board.legal_moves();
                     for legal_move in possible_moves {
-                        board
-                            .make_move(legal_move as u32, board.size * board.size)
-                            .unwrap();
-                        let local_result = min_max(board, _depth - 1, board.player_turn);
+                        board.apply(legal_move).unwrap();
+                        let local_result = min_max(board, _depth - 1, board.side_to_move());
                         if local_result.result < best_score {
                             best_score = local_result.result;
                         }
-                        board.undo_last_move().unwrap();
+                        board.undo().unwrap();
                     }
                     return SearchStats {
                         result: best_score,
@@ -1130,11 +1712,11 @@ fn min_max(board: &mut Board, _depth: i16, max_player: Player) -> SearchStats {
     }
 }
 
-fn min_max_lookup(
-    board: &mut Board,
+fn min_max_lookup<G: GameRules>(
+    board: &mut G,
     _depth: i16,
     max_player: Player,
-    look_up: &mut HashMap<Board, GameResult>,
+    look_up: &mut HashMap<G, GameResult>,
 ) -> SearchStats {
     let start_time = Instant::now();
     // Lookup in HashMap, if we visited this board just return its result.
@@ -1148,7 +1730,7 @@ fn min_max_lookup(
             unsafe {
                 COUNTER += 1;
             }
-            let result = board.get_result();
+            let result = board.result();
             match result {
                 GameResult::Draw => {
                     look_up.insert(board.clone(), result);
@@ -1171,24 +1753,16 @@ fn min_max_lookup(
                         Player::X => {
                             // We want to maximize the score
                             let mut best_score = GameResult::Player(Player::O);
-                            let possible_moves: Vec<usize> = board
-                                .fields
-                                .iter()
-                                .enumerate()
-                                .filter(|(_, value)| **value == Field::Free)
-                                .map(|(index, _)| index)
-                                .collect();
+                            let possible_moves = board.legal_moves();
                             //println!("{:?}", possible_moves.len());
                             for legal_move in possible_moves {
-                                board
-                                    .make_move(legal_move as u32, board.size * board.size)
-                                    .unwrap();
+                                board.apply(legal_move).unwrap();
                                 let local_result =
-                                    min_max_lookup(board, _depth - 1, board.player_turn, look_up);
+                                    min_max_lookup(board, _depth - 1, board.side_to_move(), look_up);
                                 if local_result.result > best_score {
                                     best_score = local_result.result;
                                 }
-                                board.undo_last_move().unwrap();
+                                board.undo().unwrap();
                             } // Here add mby?
                             look_up.insert(board.clone(), best_score);
                             return SearchStats {
@@ -1199,23 +1773,15 @@ fn min_max_lookup(
                         }
                         Player::O => {
                             let mut best_score = GameResult::Player(Player::X);
-                            let possible_moves: Vec<usize> = board
-                                .fields
-                                .iter()
-                                .enumerate()
-                                .filter(|(_, value)| **value == Field::Free)
-                                .map(|(index, _)| index)
-                                .collect();
+                            let possible_moves = board.legal_moves();
                             for legal_move in possible_moves {
-                                board
-                                    .make_move(legal_move as u32, board.size * board.size)
-                                    .unwrap();
+                                board.apply(legal_move).unwrap();
                                 let local_result =
-                                    min_max_lookup(board, _depth - 1, board.player_turn, look_up);
+                                    min_max_lookup(board, _depth - 1, board.side_to_move(), look_up);
                                 if local_result.result < best_score {
                                     best_score = local_result.result;
                                 }
-                                board.undo_last_move().unwrap();
+                                board.undo().unwrap();
                             }
                             // Adding here results in adding game-in-progress
                             look_up.insert(board.clone(), best_score);
@@ -1232,15 +1798,15 @@ fn min_max_lookup(
     }
 }
 
-fn min_max_lookup_sym(
-    board: &mut Board,
+fn min_max_lookup_sym<G: GameRules>(
+    board: &mut G,
     _depth: i16,
     max_player: Player,
-    look_up: &mut HashMap<Board, GameResult>,
+    look_up: &mut HashMap<G, GameResult>,
 ) -> SearchStats {
     let start_time = Instant::now();
     // Lookup in HashMap, if we visited this board just return its result.
-    let lookup_result = check_for_rotation(&mut board.clone(), look_up);
+    let lookup_result = check_for_symmetry(board, look_up);
     match lookup_result {
         Some(res) => SearchStats {
             result: res,
@@ -1251,7 +1817,7 @@ fn min_max_lookup_sym(
             unsafe {
                 COUNTER += 1;
             }
-            let result = board.get_result();
+            let result = board.result();
             match result {
                 GameResult::Draw => {
                     look_up.insert(board.clone(), result);
@@ -1274,28 +1840,20 @@ fn min_max_lookup_sym(
                         Player::X => {
                             // We want to maximize the score
                             let mut best_score = GameResult::Player(Player::O);
-                            let possible_moves: Vec<usize> = board
-                                .fields
-                                .iter()
-                                .enumerate()
-                                .filter(|(_, value)| **value == Field::Free)
-                                .map(|(index, _)| index)
-                                .collect();
+                            let possible_moves = board.legal_moves();
                             //println!("{:?}", possible_moves.len());
                             for legal_move in possible_moves {
-                                board
-                                    .make_move(legal_move as u32, board.size * board.size)
-                                    .unwrap();
+                                board.apply(legal_move).unwrap();
                                 let local_result = min_max_lookup_sym(
                                     board,
                                     _depth - 1,
-                                    board.player_turn,
+                                    board.side_to_move(),
                                     look_up,
                                 );
                                 if local_result.result > best_score {
                                     best_score = local_result.result;
                                 }
-                                board.undo_last_move().unwrap();
+                                board.undo().unwrap();
                             } // Here add mby?
                             look_up.insert(board.clone(), best_score);
                             return SearchStats {
@@ -1306,27 +1864,19 @@ fn min_max_lookup_sym(
                         }
                         Player::O => {
                             let mut best_score = GameResult::Player(Player::X);
-                            let possible_moves: Vec<usize> = board
-                                .fields
-                                .iter()
-                                .enumerate()
-                                .filter(|(_, value)| **value == Field::Free)
-                                .map(|(index, _)| index)
-                                .collect();
+                            let possible_moves = board.legal_moves();
                             for legal_move in possible_moves {
-                                board
-                                    .make_move(legal_move as u32, board.size * board.size)
-                                    .unwrap();
+                                board.apply(legal_move).unwrap();
                                 let local_result = min_max_lookup_sym(
                                     board,
                                     _depth - 1,
-                                    board.player_turn,
+                                    board.side_to_move(),
                                     look_up,
                                 );
                                 if local_result.result < best_score {
                                     best_score = local_result.result;
                                 }
-                                board.undo_last_move().unwrap();
+                                board.undo().unwrap();
                             }
                             // Adding here results in adding game-in-progress
                             look_up.insert(board.clone(), best_score);
@@ -1347,6 +1897,13 @@ fn min_max_lookup_sym(
 struct Game {
     board: Board,
     winner: GameResult,
+    // Strength of the AI opponent, 1 (weakest) to MAX_LEVEL (perfect)
+    level: u8,
+    tie_break: TieBreak,
+    // Search during the opponent's turn, only used at MAX_LEVEL with TieBreak::First
+    // and not before the first move, when it would mean solving the whole game
+    ponder: bool,
+    lookup: HashMap<Board, GameResult>,
 }
 
 impl Game {
@@ -1354,6 +1911,17 @@ impl Game {
         Self {
             board: Board::create_board(n),
             winner: GameResult::InProgress,
+            level: MAX_LEVEL,
+            tie_break: TieBreak::First,
+            ponder: false,
+            lookup: HashMap::new(),
+        }
+    }
+
+    fn new_misere(n: u32) -> Self {
+        Self {
+            board: Board::create_misere_board(n, n),
+            ..Self::new(n)
         }
     }
 
@@ -1364,25 +1932,29 @@ impl Game {
         }
     }
 
+    // Asks again until the input is a legal move
     fn human_move(&mut self) {
-        let mut user_move = String::new();
-        io::stdin()
-            .read_line(&mut user_move)
-            .expect("Failed to read input");
-        let index = user_move.trim_end().parse::<usize>().unwrap();
-        self.board
-            .make_move(index as u32, self.board.size * self.board.size)
-            .unwrap();
+        loop {
+            let mut user_move = String::new();
+            let read = io::stdin()
+                .read_line(&mut user_move)
+                .expect("Failed to read input");
+            if read == 0 {
+                println!("Input ended before the game did");
+                std::process::exit(1);
+            }
+            let Ok(index) = user_move.trim_end().parse::<u32>() else {
+                println!("Give a field number");
+                continue;
+            };
+            match self.board.make_move(index, self.board.size * self.board.size) {
+                Ok(()) => return,
+                Err(e) => println!("{}", e),
+            }
+        }
     }
     fn make_rand_move(&mut self) {
-        let possible_moves: Vec<usize> = self
-            .board
-            .fields
-            .iter()
-            .enumerate()
-            .filter(|(_, value)| **value == Field::Free)
-            .map(|(index, _)| index)
-            .collect();
+        let possible_moves = self.board.free_moves();
         let rng = rand::thread_rng().gen_range(0..possible_moves.len());
         let rng_move = possible_moves[rng];
         print!("{:?}", possible_moves.len());
@@ -1408,14 +1980,122 @@ impl Game {
             .unwrap();
     }
 
-    fn make_best_move_lookup(&mut self) {}
+    fn searches_with_lookup(&self) -> bool {
+        self.level == MAX_LEVEL && self.tie_break == TieBreak::First
+    }
+
+    fn make_move_at_level(&mut self) {
+        if self.searches_with_lookup() {
+            self.make_best_move_lookup();
+            return;
+        }
+        self.board
+            .make_move(
+                self.board.find_move_at_level(self.level, self.tie_break),
+                self.board.size * self.board.size,
+            )
+            .unwrap();
+    }
+
+    fn make_database_move(&mut self, database: &retrograde::Database) {
+        self.board
+            .make_move(
+                database.best_move(&self.board),
+                self.board.size * self.board.size,
+            )
+            .unwrap();
+    }
+
+    // Starts from the solved positions of earlier sessions, see cache.rs
+    fn load_lookup(&mut self) {
+        let path = cache::default_path(&self.board);
+        match cache::load(&path, &self.board) {
+            Ok(lookup) => {
+                println!("Loaded {} positions from {}", lookup.len(), path);
+                self.lookup = lookup;
+            }
+            Err(e) if e.kind() != io::ErrorKind::NotFound => println!("Ignoring {}: {}", path, e),
+            Err(_) => {}
+        }
+    }
+
+    fn save_lookup(&self) {
+        let path = cache::default_path(&self.board);
+        cache::save(&path, &self.board, &self.lookup)
+            .expect("Failed to save position cache");
+    }
+
+    fn make_best_move_lookup(&mut self) {
+        let index = self.board.find_best_move_lookup(&mut self.lookup);
+        self.board
+            .make_move(index, self.board.size * self.board.size)
+            .unwrap();
+    }
+
+    // Three Men's Morris style: `pieces` placements each, then the pieces slide. A field
+    // number places a piece, "from to" slides one. At MAX_LEVEL the AI plays from the
+    // solved positions, below it searches level plies.
+    fn play_sliding(&mut self, pieces: usize) {
+        let mut game = morris::Morris::create_game(self.board.size, pieces);
+        let table = morris::solve(&game);
+        println!("{:?}", game);
+        while game.result() == GameResult::InProgress {
+            let m = if game.side_to_move() == Player::X {
+                let mut user_move = String::new();
+                io::stdin()
+                    .read_line(&mut user_move)
+                    .expect("Failed to read input");
+                let numbers: Vec<u32> = user_move.split_whitespace().filter_map(|n| n.parse().ok()).collect();
+                match numbers[..] {
+                    [field] => morris::MorrisMove::Place(field),
+                    [from, to] => morris::MorrisMove::Slide(from, to),
+                    _ => {
+                        println!("Give a field, or two to slide from and to");
+                        continue;
+                    }
+                }
+            } else if self.level == MAX_LEVEL {
+                morris::best_move(&table, &game)
+            } else {
+                best_move_depth(&game, self.level as i8, &morris::morris_eval)
+            };
+            if let Err(e) = game.apply(m) {
+                println!("{}", e);
+                continue;
+            }
+            println!("{:?}", game);
+        }
+        self.board = game.board.clone();
+        self.winner = game.result();
+        match self.winner {
+            GameResult::Player(winner) => println!("Winner is {:?}", winner),
+            _ => println!("Good game, Draw!"),
+        }
+    }
 
     fn play(&mut self) {
+        if self.searches_with_lookup() {
+            self.load_lookup();
+        }
         loop {
+            let pondering = if self.ponder && self.searches_with_lookup() && !self.board.moves.is_empty() {
+                let lookup = std::mem::take(&mut self.lookup);
+                Some(ponder::Ponder::start(self.board.clone(), lookup))
+            } else {
+                None
+            };
             self.human_move();
+            let mut ponder_answer = None;
+            if let Some(pondering) = pondering {
+                let (answer, lookup) = pondering.finish(*self.board.moves.last().unwrap());
+                ponder_answer = answer;
+                self.lookup = lookup;
+            }
             println!("{:?}", self.board);
             println!("{:?}", self.board.lines_heuristic(Player::X));
-            println!("{:?}", self.board.better_heuristic(Player::X));
+            if self.board.square_layout() {
+                println!("{:?}", self.board.better_heuristic(Player::X));
+            }
             self.winner = self.board.get_result();
             match self.winner {
                 GameResult::Player(_) => {
@@ -1425,12 +2105,20 @@ impl Game {
                 }
                 GameResult::Draw => {
                     println!("Good game, Draw!");
-                    return;
+                    break;
                 }
                 _ => {}
             }
             //println!("{:?}", self.board.generate_sorted_lines_heuristic());
-            self.make_best_move_a_b();
+            match ponder_answer {
+                Some(index) => {
+                    println!("Ponder hit");
+                    self.board
+                        .make_move(index, self.board.size * self.board.size)
+                        .unwrap();
+                }
+                None => self.make_move_at_level(),
+            }
             println!("{:?}", self.board);
             println!("{:?}", self.board.lines_heuristic(Player::O));
             self.winner = self.board.get_result();
@@ -1442,12 +2130,15 @@ impl Game {
                 }
                 GameResult::Draw => {
                     println!("Good game, Draw!");
-                    return;
+                    break;
                 }
                 _ => {}
             }
             println!("{:?}", self.board.moves);
         }
+        if self.searches_with_lookup() {
+            self.save_lookup();
+        }
     }
 }
 // This is very bad, but i don't know how to do it idiomatically
@@ -1455,6 +2146,8 @@ impl Game {
 // I'm sorry :(
 
 static mut COUNTER: i32 = 0;
+// Stop flag of the searches nobody interrupts
+static NO_STOP: AtomicBool = AtomicBool::new(false);
 
 fn main() {
     assert!(Player::X > Player::O);
@@ -1465,6 +2158,663 @@ fn main() {
     assert!(GameResult::Player(Player::O) < GameResult::InProgress);
     assert_ne!(GameResult::InProgress > GameResult::Draw, true);
     assert_ne!(GameResult::InProgress < GameResult::Draw, true);
+
+    let args: Vec<String> = std::env::args().collect();
+    // Trailing "misere" and "torus" switch play, solve, retrograde, rank, stats and mcts
+    // to misere rules and to a board whose edges wrap around
+    let flag = |name: &str| args.iter().skip(2).any(|a| a == name);
+    let misere = flag("misere");
+    let torus = flag("torus");
+    // Also for the game without a command
+    let ponder = args.iter().skip(1).any(|a| a == "ponder");
+    let new_board = |size: u32, win_length: u32| {
+        let board = if misere {
+            Board::create_misere_board(size, win_length)
+        } else {
+            Board::create_board_k(size, win_length)
+        };
+        Board { torus, ..board }
+    };
+    match args.get(1).map(|s| s.as_str()) {
+        Some("qlearn") => {
+            // qlearn [episodes] [learning_rate] [exploration] [file]
+            let episodes = args.get(2).and_then(|a| a.parse().ok()).unwrap_or(50000);
+            let learning_rate = args.get(3).and_then(|a| a.parse().ok()).unwrap_or(0.2);
+            let exploration = args.get(4).and_then(|a| a.parse().ok()).unwrap_or(0.1);
+            let path = args.get(5).map(|s| s.as_str()).unwrap_or("qtable_3x3.txt");
+
+            let mut agent = qlearning::QLearning::new(3, learning_rate, exploration, episodes);
+            if agent.load(path).is_ok() {
+                println!("Loaded {} positions from {}", agent.positions(), path);
+            }
+            let start_time = Instant::now();
+            agent.train();
+            println!("Trained {} episodes in {:?}", episodes, start_time.elapsed());
+            println!("Known positions: {}", agent.positions());
+            agent.save(path).expect("Failed to save table");
+
+            println!("Learned vs Min-Max (wins, draws, losses):");
+            println!("{:?}", agent.play_against(Board::find_best_move, 2));
+            println!("Learned vs Random (wins, draws, losses):");
+            println!("{:?}", agent.play_against(qlearning::random_move, 1000));
+            return;
+        }
+        Some("ntuple") => {
+            // ntuple [size] [win_length] [episodes] [file]
+            let size = args.get(2).and_then(|a| a.parse().ok()).unwrap_or(7);
+            let win_length = args.get(3).and_then(|a| a.parse().ok()).unwrap_or(4);
+            let episodes = args.get(4).and_then(|a| a.parse().ok()).unwrap_or(2000);
+            let default_path = format!("ntuple_{}x{}_k{}.txt", size, size, win_length);
+            let path = args.get(5).unwrap_or(&default_path);
+
+            let mut network = ntuple::NTuple::new(size, win_length, win_length, 0.01, 0.1, episodes);
+            if network.load(path).is_ok() {
+                println!("Loaded network from {}", path);
+            }
+            let start_time = Instant::now();
+            network.train();
+            println!("Trained {} episodes in {:?}", episodes, start_time.elapsed());
+            network.save(path).expect("Failed to save network");
+
+            // N-tuple horizon vs lines_heuristic horizon, both at depth 2, one game per side
+            let network_eval = |b: &Board| network.eval(b);
+            for network_player in [Player::X, Player::O] {
+                let mut board = Board::create_board_k(size, win_length);
+                while board.get_result() == GameResult::InProgress {
+                    let index = if board.player_turn == network_player {
+                        board.find_best_move_depth(2, &network_eval)
+                    } else {
+                        board.find_best_move_depth(2, &lines_eval)
+                    };
+                    board.make_move(index, size * size).unwrap();
+                }
+                println!("{:?}", board);
+                println!("N-tuple as {:?}: {:?}", network_player, board.get_result());
+            }
+            return;
+        }
+        Some("play") => {
+            // play [size] [level] [exploit] [misere] [torus] [ponder]
+            let size = args.get(2).and_then(|a| a.parse().ok()).unwrap_or(3);
+            let mut game = if misere { Game::new_misere(size) } else { Game::new(size) };
+            game.board.torus = torus;
+            game.ponder = ponder;
+            game.level = args.get(3).and_then(|a| a.parse().ok()).unwrap_or(MAX_LEVEL);
+            if args.get(4).map(|s| s.as_str()) == Some("exploit") {
+                game.tie_break = TieBreak::Exploit;
+            }
+            game.play();
+            return;
+        }
+        Some("connect") => {
+            // connect [width] [height] [win_length] [level] [ponder], MAX_LEVEL only finishes on small boards
+            let width = args.get(2).and_then(|a| a.parse().ok()).unwrap_or(7);
+            let height = args.get(3).and_then(|a| a.parse().ok()).unwrap_or(6);
+            let win_length = args.get(4).and_then(|a| a.parse().ok()).unwrap_or(4);
+            let mut game = Game::new(width);
+            game.board = Board::create_gravity_board(width, height, win_length);
+            game.level = args.get(5).and_then(|a| a.parse().ok()).unwrap_or(MAX_LEVEL - 1);
+            game.ponder = ponder;
+            println!("{:?}", game.board);
+            game.play();
+            return;
+        }
+        Some("solve") => {
+            // solve [size] [win_length] [misere] [torus]
+            let size = args.get(2).and_then(|a| a.parse().ok()).unwrap_or(3);
+            let win_length = args.get(3).and_then(|a| a.parse().ok()).unwrap_or(size);
+            let mut game = Game::new(size);
+            game.board = new_board(size, win_length);
+            game.load_lookup();
+            let start_time = Instant::now();
+            game.board.find_best_move_lookup(&mut game.lookup);
+            println!("{} positions in {:?}", game.lookup.len(), start_time.elapsed());
+            game.save_lookup();
+            return;
+        }
+        Some("retrograde") => {
+            // retrograde [size] [win_length] [misere] [torus]
+            let size = args.get(2).and_then(|a| a.parse().ok()).unwrap_or(3);
+            let win_length = args.get(3).and_then(|a| a.parse().ok()).unwrap_or(size);
+            let start_time = Instant::now();
+            let database = retrograde::Database::solve(new_board(size, win_length));
+            println!("{} positions in {:?}", database.positions(), start_time.elapsed());
+            let root = database.get(&database.start).unwrap();
+            println!("Empty board: {:?} in {} plies", root.result, root.distance);
+            let mut by_result = HashMap::new();
+            for (_, entry) in database.boards() {
+                *by_result.entry(format!("{:?}", entry.result)).or_insert(0) += 1;
+            }
+            println!("{:?}", by_result);
+
+            // Every engine checked against the database, on all positions of small boards
+            let alfa = GameResult::Player(Player::O);
+            let beta = GameResult::Player(Player::X);
+            let mut engines: Vec<(&str, retrograde::Engine)> = vec![
+                ("Min-Max", Box::new(|b: &mut Board| min_max(b, 10, b.player_turn).result)),
+                ("Min-Max Lookup", Box::new(|b: &mut Board| {
+                    min_max_lookup(b, 10, b.player_turn, &mut HashMap::new()).result
+                })),
+                ("Min-Max Lookup-Sym", Box::new(|b: &mut Board| {
+                    min_max_lookup_sym(b, 10, b.player_turn, &mut HashMap::new()).result
+                })),
+                ("AB", Box::new(move |b: &mut Board| alpha_beta(b, 10, alfa, beta, b.player_turn).result)),
+                ("ABH1", Box::new(move |b: &mut Board| alpha_beta_h1(b, 10, alfa, beta, b.player_turn).result)),
+                ("ABH2", Box::new(move |b: &mut Board| alpha_beta_h2(b, 10, alfa, beta, b.player_turn).result)),
+                ("AB Lookup", Box::new(move |b: &mut Board| {
+                    alpha_beta_lookup(b, 10, alfa, beta, b.player_turn, &mut HashMap::new()).result
+                })),
+                ("AB Lookup Sym", Box::new(move |b: &mut Board| {
+                    alpha_beta_lookup_sym(b, 10, alfa, beta, b.player_turn, &mut HashMap::new()).result
+                })),
+            ];
+            for (name, engine) in engines.iter_mut() {
+                println!("{}: {} mismatches", name, database.verify(engine, 20000));
+            }
+
+            let mut game = Game::new(size);
+            game.board = new_board(size, win_length);
+            while game.board.get_result() == GameResult::InProgress {
+                game.make_database_move(&database);
+            }
+            println!("{:?}", game.board);
+            println!("{:?}", game.board.moves);
+            return;
+        }
+        Some("rank") => {
+            // rank [size] [win_length] [misere] [torus]
+            let size = args.get(2).and_then(|a| a.parse().ok()).unwrap_or(3);
+            let win_length = args.get(3).and_then(|a| a.parse().ok()).unwrap_or(size);
+            let start_time = Instant::now();
+            let symmetric = ranking::SymmetricRanking::new(&new_board(size, win_length));
+            let plain = &symmetric.ranking;
+            println!("{} positions, {} up to symmetry, in {:?}", plain.positions(), symmetric.positions(), start_time.elapsed());
+            let round_trip = (0..plain.positions()).all(|i| plain.rank(&plain.unrank(i)) == i)
+                && (0..symmetric.positions()).all(|i| symmetric.rank(&symmetric.unrank(i)) == i);
+            println!("Rank/unrank round trip: {}", round_trip);
+
+            let database = retrograde::Database::solve(new_board(size, win_length));
+            let table = ranking::PackedTable::from_database(&database, &|b| plain.rank(b), plain.positions());
+            let sym_table =
+                ranking::PackedTable::from_database(&database, &|b| symmetric.rank(b), symmetric.positions());
+            let mismatches = database
+                .boards()
+                .filter(|(b, e)| table.get(plain.rank(b)) != e.result || sym_table.get(symmetric.rank(b)) != e.result)
+                .count();
+            println!(
+                "{} solved positions in {} bytes, {} bytes up to symmetry, {} mismatches",
+                database.positions(),
+                table.bytes(),
+                sym_table.bytes(),
+                mismatches
+            );
+            return;
+        }
+        Some("stats") => {
+            // stats [size] [win_length] [misere] [torus]
+            let size = args.get(2).and_then(|a| a.parse().ok()).unwrap_or(3);
+            let win_length = args.get(3).and_then(|a| a.parse().ok()).unwrap_or(size);
+            let start_time = Instant::now();
+            let tree = stats::tree_stats(&new_board(size, win_length));
+            println!("Walked the tree in {:?}", start_time.elapsed());
+            println!("Games: {}", tree.games);
+            println!("X wins: {}", tree.x_wins_by_ply.iter().sum::<u64>());
+            println!("O wins: {}", tree.o_wins_by_ply.iter().sum::<u64>());
+            println!("Draws: {}", tree.draws_by_ply.iter().sum::<u64>());
+            println!("Ply | X wins | O wins | Draws");
+            for ply in 0..tree.x_wins_by_ply.len() {
+                println!(
+                    "{:>3} | {:>6} | {:>6} | {:>5}",
+                    ply, tree.x_wins_by_ply[ply], tree.o_wins_by_ply[ply], tree.draws_by_ply[ply]
+                );
+            }
+            println!("Distinct positions: {}", tree.positions);
+            println!("Distinct positions up to symmetry: {}", tree.symmetric_positions);
+            println!(
+                "Terminal positions: {} X wins, {} O wins, {} draws",
+                tree.terminal_x_wins, tree.terminal_o_wins, tree.terminal_draws
+            );
+            println!("Average branching factor: {:.3}", tree.average_branching());
+            return;
+        }
+        Some("perft") => {
+            // perft [size] [depth] [moves, comma separated]
+            let size = args.get(2).and_then(|a| a.parse().ok()).unwrap_or(3);
+            let depth = args.get(3).and_then(|a| a.parse().ok()).unwrap_or(size * size);
+            let mut board = Board::create_board(size);
+            if let Some(moves) = args.get(4) {
+                for index in moves.split(',') {
+                    let index = index.parse().expect("Moves must be field numbers");
+                    board.make_move(index, size * size).unwrap();
+                }
+            }
+            println!("{:?}", board);
+            let reference = perft::reference(&board);
+            println!("Depth | make/undo | clone | reference");
+            for d in 0..=depth {
+                let start_time = Instant::now();
+                let nodes = perft::perft(&mut board, d).unwrap();
+                let cloned = perft::perft_clone(&board, d);
+                let expected = reference.and_then(|r| r.get(d as usize).copied());
+                println!("{:>5} | {:>9} | {:>5} | {:?} {:?}", d, nodes, cloned, expected, start_time.elapsed());
+                assert_eq!(nodes, cloned, "make/undo and clone move generation disagree");
+                if let Some(expected) = expected {
+                    assert_eq!(nodes, expected, "Perft does not match the reference");
+                }
+            }
+            let games = perft::complete_games(&mut board).unwrap();
+            println!("Complete games: {}", games);
+            if reference.is_some() {
+                assert_eq!(games, perft::GAMES_3X3, "Complete games do not match the reference");
+            }
+            return;
+        }
+        Some("mcts") => {
+            // mcts [size] [win_length] [iterations] [misere] [torus]
+            let size = args.get(2).and_then(|a| a.parse().ok()).unwrap_or(3);
+            let win_length = args.get(3).and_then(|a| a.parse().ok()).unwrap_or(size);
+            let iterations = args.get(4).and_then(|a| a.parse().ok()).unwrap_or(5000);
+            let mcts = |b: &Board| mcts::mcts_best_move(b, iterations, 1.4);
+            let alpha_beta = |b: &Board| best_move_alpha_beta(b, &mut HashMap::new());
+            let board = new_board(size, win_length);
+            let game = play_out(board.clone(), &mcts, &alpha_beta);
+            println!("{:?}", game);
+            println!("MCTS as X vs Alpha-Beta: {:?}", game.result());
+            let game = play_out(board, &alpha_beta, &mcts);
+            println!("{:?}", game);
+            println!("Alpha-Beta vs MCTS as O: {:?}", game.result());
+            return;
+        }
+        Some("cube") => {
+            // cube [size] [depth] [iterations]
+            let size = args.get(2).and_then(|a| a.parse().ok()).unwrap_or(4);
+            let depth = args.get(3).and_then(|a| a.parse().ok()).unwrap_or(3);
+            let iterations = args.get(4).and_then(|a| a.parse().ok()).unwrap_or(5000);
+            let cube = cube::Cube::create_cube(size);
+            println!("{} lines, {} symmetries", cube.lines().len(), cube.symmetries().len() + 1);
+            let normalize = |mut line: Vec<usize>| {
+                line.sort();
+                line
+            };
+            let line_set: HashSet<Vec<usize>> = cube.lines().iter().map(|l| normalize(l.clone())).collect();
+            let preserved = cube::cube_symmetries(size)
+                .iter()
+                .all(|s| cube.lines().iter().all(|l| line_set.contains(&normalize(l.iter().map(|&f| s[f]).collect()))));
+            println!("Symmetries map lines to lines: {}", preserved);
+
+            if size <= 3 {
+                // Exact solution, X tries the ordered moves (wins and blocks first)
+                let alfa = GameResult::Player(Player::O);
+                let beta = GameResult::Player(Player::X);
+                let start_time = Instant::now();
+                let mut lookup = HashMap::new();
+                let result = pre_alpha_beta_look(
+                    &mut cube.clone(),
+                    alfa,
+                    beta,
+                    Player::X,
+                    |c: &cube::Cube| c.ordered_moves(),
+                    &mut lookup,
+                    &NO_STOP,
+                )
+                .result;
+                println!("Empty cube: {:?}, {} positions in {:?}", result, lookup.len(), start_time.elapsed());
+            }
+
+            let mcts = |c: &cube::Cube| mcts::mcts_best_move(c, iterations, 1.4);
+            let alpha_beta = |c: &cube::Cube| best_move_depth(c, depth, &cube::cube_eval);
+            let game = play_out(cube.clone(), &alpha_beta, &mcts);
+            println!("{:?}", game);
+            println!("Alpha-Beta as X vs MCTS: {:?}", game.result());
+            let game = play_out(cube, &mcts, &alpha_beta);
+            println!("{:?}", game);
+            println!("MCTS as X vs Alpha-Beta: {:?}", game.result());
+            return;
+        }
+        Some("wild") => {
+            // wild [size] [win_length] [depth] [iterations]
+            let size = args.get(2).and_then(|a| a.parse().ok()).unwrap_or(3);
+            let win_length = args.get(3).and_then(|a| a.parse().ok()).unwrap_or(size);
+            let depth = args.get(4).and_then(|a| a.parse().ok()).unwrap_or(4);
+            let iterations = args.get(5).and_then(|a| a.parse().ok()).unwrap_or(5000);
+            let wild = wild::Wild::create_game(size, win_length);
+
+            if size <= 3 {
+                let alfa = GameResult::Player(Player::O);
+                let beta = GameResult::Player(Player::X);
+                let start_time = Instant::now();
+                let mut lookup = HashMap::new();
+                let result = pre_alpha_beta_look_sym(
+                    &mut wild.clone(),
+                    20,
+                    alfa,
+                    beta,
+                    Player::X,
+                    |w: &wild::Wild| w.ordered_moves(),
+                    &mut lookup,
+                )
+                .result;
+                println!("Empty board: {:?}, {} positions in {:?}", result, lookup.len(), start_time.elapsed());
+            }
+
+            let mcts = |w: &wild::Wild| mcts::mcts_best_move(w, iterations, 1.4);
+            let alpha_beta = |w: &wild::Wild| best_move_depth(w, depth, &wild::wild_eval);
+            let game = play_out(wild.clone(), &alpha_beta, &mcts);
+            println!("{:?}", game);
+            println!("Alpha-Beta as X vs MCTS: {:?}", game.result());
+            let game = play_out(wild, &mcts, &alpha_beta);
+            println!("{:?}", game);
+            println!("MCTS as X vs Alpha-Beta: {:?}", game.result());
+            return;
+        }
+        Some("notakto") => {
+            // notakto [boards] [samples], alpha-beta gets slow from 3 boards on
+            let boards = args.get(2).and_then(|a| a.parse().ok()).unwrap_or(2);
+            let samples = args.get(3).and_then(|a| a.parse().ok()).unwrap_or(200);
+            let mut solver = notakto::Solver::new();
+            let start_time = Instant::now();
+            let mismatches = solver.check();
+            println!(
+                "{} board classes, {} mismatches with brute force on sums of up to 3 boards, in {:?}",
+                solver.board_classes(),
+                mismatches,
+                start_time.elapsed()
+            );
+            println!("Empty board: {}", solver.value(0));
+            for n in 1..=boards {
+                let winner = if solver.lost(&vec![0; n]) { "Second" } else { "First" };
+                println!("{} empty boards: {} player wins", n, winner);
+            }
+
+            // The quotient against alpha-beta on the full multi-board state
+            let alfa = GameResult::Player(Player::O);
+            let beta = GameResult::Player(Player::X);
+            let start_time = Instant::now();
+            let mut mismatches = 0;
+            for _ in 0..samples {
+                let mut game = notakto::Notakto::random_position(boards);
+                let player = game.side_to_move();
+                let result = alpha_beta_lookup(&mut game, 30, alfa, beta, player, &mut HashMap::new()).result;
+                if (result == GameResult::Player(player.opponent())) != solver.lost(&game.masks()) {
+                    mismatches += 1;
+                }
+            }
+            println!("{} random positions, {} mismatches, in {:?}", samples, mismatches, start_time.elapsed());
+            // Larger sums only against the solver's own brute force, on bit masks
+            let mismatches = (0..samples)
+                .filter(|_| {
+                    let masks = notakto::Notakto::random_position(rand::thread_rng().gen_range(1..=4)).masks();
+                    solver.lost(&masks) != solver.brute_force_lost(&masks)
+                })
+                .count();
+            println!("{} random sums of up to 4 boards, {} mismatches", samples, mismatches);
+
+            let quotient = |g: &notakto::Notakto| solver.best_move(g);
+            let mcts = |g: &notakto::Notakto| mcts::mcts_best_move(g, 5000, 1.4);
+            let game = play_out(notakto::Notakto::create_game(boards), &quotient, &mcts);
+            println!("{:?}", game);
+            println!("Quotient as X vs MCTS: {:?}", game.result());
+            let game = play_out(notakto::Notakto::create_game(boards), &mcts, &quotient);
+            println!("{:?}", game);
+            println!("MCTS as X vs Quotient: {:?}", game.result());
+            return;
+        }
+        Some("quantum") => {
+            // quantum [depth] [iterations] [games]
+            let depth = args.get(2).and_then(|a| a.parse().ok()).unwrap_or(3);
+            let iterations = args.get(3).and_then(|a| a.parse().ok()).unwrap_or(3000);
+            let games = args.get(4).and_then(|a| a.parse().ok()).unwrap_or(20);
+            let random = |q: &quantum::Quantum| quantum::random_move(q);
+            let alpha_beta = |q: &quantum::Quantum| best_move_depth(q, depth, &quantum::quantum_eval);
+            let mcts = |q: &quantum::Quantum| mcts::mcts_best_move(q, iterations, 1.4);
+
+            let engines: [(&str, quantum::Engine); 3] = [("Random", &random), ("Alpha-Beta", &alpha_beta), ("MCTS", &mcts)];
+            for (x_name, x_engine) in engines {
+                for (o_name, o_engine) in engines {
+                    if x_name == o_name {
+                        continue;
+                    }
+                    let mut points = (0.0, 0.0);
+                    for _ in 0..games {
+                        let game = play_out(quantum::Quantum::create_game(), x_engine, o_engine);
+                        let (x, o) = game.score();
+                        points = (points.0 + x, points.1 + o);
+                    }
+                    println!("{} as X vs {} as O: {:?} points in {} games", x_name, o_name, points, games);
+                }
+            }
+            let game = play_out(quantum::Quantum::create_game(), &alpha_beta, &mcts);
+            println!("{:?}", game);
+            println!("Alpha-Beta as X vs MCTS: {:?} {:?}", game.result(), game.score());
+            return;
+        }
+        Some("orderchaos") => {
+            // orderchaos [depth] [iterations]
+            let depth = args.get(2).and_then(|a| a.parse().ok()).unwrap_or(2);
+            let iterations = args.get(3).and_then(|a| a.parse().ok()).unwrap_or(2000);
+            let alpha_beta = |g: &order_chaos::OrderChaos| best_move_depth(g, depth, &order_chaos::order_chaos_eval);
+            let mcts = |g: &order_chaos::OrderChaos| mcts::mcts_best_move(g, iterations, 1.4);
+            let engines: [(&str, order_chaos::Engine); 2] = [("Alpha-Beta", &alpha_beta), ("MCTS", &mcts)];
+            // Every engine in both roles, and each role held by both X and O
+            for order in [Player::X, Player::O] {
+                for (order_name, order_engine) in engines {
+                    for (chaos_name, chaos_engine) in engines {
+                        let (x_engine, o_engine) = match order {
+                            Player::X => (order_engine, chaos_engine),
+                            Player::O => (chaos_engine, order_engine),
+                        };
+                        let game = play_out(order_chaos::OrderChaos::create_game(order), x_engine, o_engine);
+                        println!(
+                            "{} as Order ({:?}) vs {} as Chaos: {:?} wins after {} moves",
+                            order_name,
+                            order,
+                            chaos_name,
+                            game.winner().unwrap(),
+                            game.board.moves.len()
+                        );
+                    }
+                }
+            }
+            let game = play_out(order_chaos::OrderChaos::create_game(Player::X), &alpha_beta, &alpha_beta);
+            println!("{:?}", game);
+            println!("{:?} wins", game.winner().unwrap());
+            return;
+        }
+        Some("gomoku") => {
+            // gomoku [size] [free|standard|renju] [depth]
+            let size: u32 = args.get(2).and_then(|a| a.parse().ok()).unwrap_or(15);
+            let rules = match args.get(3).map(|s| s.as_str()) {
+                Some("free") => RuleSet::FreeStyle,
+                Some("standard") => RuleSet::Standard,
+                _ => RuleSet::Renju,
+            };
+            let depth = args.get(4).and_then(|a| a.parse().ok()).unwrap_or(1);
+
+            if size >= 11 {
+                let mut right = 0;
+                for (name, x_stones, o_stones, expected) in RENJU_SHAPES {
+                    let (board, center) = renju_shape(size, x_stones, o_stones);
+                    if board.is_forbidden(center) == expected {
+                        right += 1;
+                    } else {
+                        println!("Wrong on {}:{:?}", name, board);
+                    }
+                }
+                println!("Forbidden move detector: {}/{} shapes right", right, RENJU_SHAPES.len());
+            }
+
+            let mut board = Board::create_gomoku_board(size, rules);
+            let start_time = Instant::now();
+            while board.get_result() == GameResult::InProgress {
+                let index = board.find_best_move_depth(depth, &lines_eval);
+                board.make_move(index, size * size).unwrap();
+            }
+            println!("{:?}", board);
+            println!(
+                "{:?} rules: {:?} after {} moves in {:?}",
+                rules,
+                board.get_result(),
+                board.moves.len(),
+                start_time.elapsed()
+            );
+            return;
+        }
+        Some("connect6") => {
+            // connect6 [size] [depth] [candidates]
+            let size = args.get(2).and_then(|a| a.parse().ok()).unwrap_or(19);
+            let depth = args.get(3).and_then(|a| a.parse().ok()).unwrap_or(2);
+            let mut game = connect6::Connect6::create_game(size, 6);
+            game.candidates = args.get(4).and_then(|a| a.parse().ok()).unwrap_or(8);
+
+            // Greedy plays the first pruned move without looking ahead
+            let greedy = |g: &connect6::Connect6| g.ordered_moves()[0];
+            let alpha_beta = |g: &connect6::Connect6| best_move_depth(g, depth, &connect6::connect6_eval);
+            let engines: [(&str, connect6::Engine); 2] = [("Alpha-Beta", &alpha_beta), ("Greedy", &greedy)];
+            for (x_name, x_engine) in engines {
+                for (o_name, o_engine) in engines {
+                    let start_time = Instant::now();
+                    let end = play_out(game.clone(), x_engine, o_engine);
+                    println!(
+                        "{} as X vs {} as O: {:?} after {} turns in {:?}",
+                        x_name,
+                        o_name,
+                        end.result(),
+                        end.board.turns.len(),
+                        start_time.elapsed()
+                    );
+                    if x_name == o_name && x_name == "Alpha-Beta" {
+                        println!("{:?}", end);
+                    }
+                }
+            }
+            return;
+        }
+        Some("disappearing") => {
+            // disappearing [size] [marks] [depth] [iterations] [move_limit]
+            let size = args.get(2).and_then(|a| a.parse().ok()).unwrap_or(3);
+            let marks = args.get(3).and_then(|a| a.parse().ok()).unwrap_or(3);
+            let depth = args.get(4).and_then(|a| a.parse().ok()).unwrap_or(8);
+            let iterations = args.get(5).and_then(|a| a.parse().ok()).unwrap_or(5000);
+            if marks == 0 {
+                println!("Each player needs at least one mark");
+                return;
+            }
+            let mut game = disappearing::Disappearing::create_game(size, marks);
+            game.move_limit = args.get(6).and_then(|a| a.parse().ok()).unwrap_or(100);
+
+            let alpha_beta = |g: &disappearing::Disappearing| best_move_depth(g, depth, &disappearing::disappearing_eval);
+            let mcts = |g: &disappearing::Disappearing| mcts::mcts_best_move(g, iterations, 1.4);
+            let engines: [(&str, disappearing::Engine); 2] = [("Alpha-Beta", &alpha_beta), ("MCTS", &mcts)];
+            for (x_name, x_engine) in engines {
+                for (o_name, o_engine) in engines {
+                    let end = play_out(game.clone(), x_engine, o_engine);
+                    let ending = if end.occurrences() >= end.repetitions { ", by repetition" } else { "" };
+                    println!(
+                        "{} as X vs {} as O: {:?} after {} plies{}",
+                        x_name,
+                        o_name,
+                        end.result(),
+                        end.board.moves.len(),
+                        ending
+                    );
+                }
+            }
+            let end = play_out(game, &alpha_beta, &alpha_beta);
+            println!("{:?}", end);
+            return;
+        }
+        Some("morris") => {
+            // morris [pieces] [depth] [iterations] [play], 3 pieces is Three Men's Morris, 4 is Achi
+            let pieces = args.get(2).and_then(|a| a.parse().ok()).unwrap_or(3);
+            let depth = args.get(3).and_then(|a| a.parse().ok()).unwrap_or(6);
+            let iterations = args.get(4).and_then(|a| a.parse().ok()).unwrap_or(5000);
+            if flag("play") {
+                let mut game = Game::new(3);
+                game.level = args.get(3).and_then(|a| a.parse().ok()).unwrap_or(MAX_LEVEL);
+                game.play_sliding(pieces);
+                return;
+            }
+            let game = morris::Morris::create_game(3, pieces);
+            let start_time = Instant::now();
+            let table = morris::solve(&game);
+            println!("{} positions in {:?}", table.len(), start_time.elapsed());
+            let root = table[&game];
+            println!("Empty board: {:?} in {} plies", root.result, root.distance);
+            let mut by_result = HashMap::new();
+            for entry in table.values() {
+                *by_result.entry(format!("{:?}", entry.result)).or_insert(0) += 1;
+            }
+            println!("{:?}", by_result);
+
+            println!("Positions that disagree with their children: {}", morris::verify(&table));
+
+            let perfect = |g: &morris::Morris| morris::best_move(&table, g);
+            let alpha_beta = |g: &morris::Morris| best_move_depth(g, depth, &morris::morris_eval);
+            let mcts = |g: &morris::Morris| mcts::mcts_best_move(g, iterations, 1.4);
+            let engines: [(&str, morris::Engine); 3] =
+                [("Perfect", &perfect), ("Alpha-Beta", &alpha_beta), ("MCTS", &mcts)];
+            for (x_name, x_engine) in engines {
+                for (o_name, o_engine) in engines {
+                    let end = play_out(game.clone(), x_engine, o_engine);
+                    let ending = if end.occurrences() >= end.repetitions { ", by repetition" } else { "" };
+                    println!("{} as X vs {} as O: {:?}{}", x_name, o_name, end.result(), ending);
+                }
+            }
+            let end = play_out(game, &perfect, &perfect);
+            println!("{:?}", end);
+            return;
+        }
+        Some("ultimate") => {
+            // ultimate [depth] [iterations]
+            let depth = args.get(2).and_then(|a| a.parse().ok()).unwrap_or(4);
+            let iterations = args.get(3).and_then(|a| a.parse().ok()).unwrap_or(5000);
+            let mcts = |g: &ultimate::Ultimate| mcts::mcts_best_move(g, iterations, 1.4);
+            let alpha_beta = |g: &ultimate::Ultimate| best_move_depth(g, depth, &ultimate::ultimate_eval);
+            let start_time = Instant::now();
+            let game = play_out(ultimate::Ultimate::create_game(), &alpha_beta, &mcts);
+            println!("{:?}", game);
+            println!("Alpha-Beta as X vs MCTS: {:?}", game.result());
+            let game = play_out(ultimate::Ultimate::create_game(), &mcts, &alpha_beta);
+            println!("{:?}", game);
+            println!("MCTS as X vs Alpha-Beta: {:?} in {:?}", game.result(), start_time.elapsed());
+            return;
+        }
+        Some("alphazero") => {
+            // alphazero [size] [win_length] [iterations] [checkpoint]
+            let size = args.get(2).and_then(|a| a.parse().ok()).unwrap_or(5);
+            let win_length = args.get(3).and_then(|a| a.parse().ok()).unwrap_or(4);
+            let iterations = args.get(4).and_then(|a| a.parse().ok()).unwrap_or(10);
+            let default_path = format!("alphazero_{}x{}_k{}.txt", size, size, win_length);
+            let path = args.get(5).unwrap_or(&default_path);
+
+            let mut trainer = alphazero::AlphaZero::new(size, win_length, 64);
+            if trainer.best.load(path).is_ok() {
+                println!("Loaded checkpoint from {}", path);
+            }
+            for i in 0..iterations {
+                let start_time = Instant::now();
+                let accepted = trainer.iteration(path).expect("Failed to save checkpoint");
+                println!("Iteration {}: accepted {} in {:?}", i, accepted, start_time.elapsed());
+            }
+
+            for network_player in [Player::X, Player::O] {
+                let mut board = Board::create_board_k(size, win_length);
+                while board.get_result() == GameResult::InProgress {
+                    let index = if board.player_turn == network_player {
+                        trainer.best_move(&board)
+                    } else {
+                        board.find_best_move_depth(2, &lines_eval)
+                    };
+                    board.make_move(index, size * size).unwrap();
+                }
+                println!("{:?}", board);
+                println!("AlphaZero as {:?}: {:?}", network_player, board.get_result());
+            }
+            return;
+        }
+        _ => {}
+    }
+
     let mut game = Game::new(3);
     let mut game1 = Game::new(4);
 
@@ -1688,5 +3038,92 @@ fn main() {
         COUNTER = 0;
     }
 
+    game1.ponder = ponder;
     game1.play();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renju_shapes() {
+        for (name, x_stones, o_stones, expected) in RENJU_SHAPES {
+            let (board, center) = renju_shape(15, x_stones, o_stones);
+            assert_eq!(board.is_forbidden(center), expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn forbidden_moves_are_only_for_x() {
+        let (_, x_stones, _, _) = RENJU_SHAPES[0];
+        let (mut board, center) = renju_shape(15, x_stones, &[]);
+        assert!(!board.free_moves().contains(&(center as u32)));
+        assert_eq!(board.make_move(center as u32, 225), Err("Forbidden move!"));
+        board.player_turn = Player::O;
+        assert_eq!(board.make_move(center as u32, 225), Ok(()));
+    }
+
+    #[test]
+    fn gravity_drops_to_the_lowest_free_field() {
+        let mut board = Board::create_gravity_board(3, 2, 2);
+        board.make_move(1, 6).unwrap();
+        assert_eq!(board.fields[4], Field::Player(Player::X));
+        board.make_move(1, 6).unwrap();
+        assert_eq!(board.fields[1], Field::Player(Player::O));
+        assert_eq!(board.make_move(1, 6), Err("Column is full!"));
+        assert_eq!(board.make_move(3, 6), Err("Wrong column number"));
+        assert_eq!(board.free_moves(), vec![0, 2]);
+        board.undo_last_move().unwrap();
+        assert_eq!(board.fields[1], Field::Free);
+        assert_eq!(board.player_turn, Player::O);
+    }
+
+    // In misère the player who completes a line loses it
+    #[test]
+    fn misere_lines_lose() {
+        for (moves, completed_by) in [([0, 3, 1, 4, 2].as_slice(), Player::X), (&[0, 3, 1, 4, 8, 5], Player::O)] {
+            for (misere, winner) in [(false, completed_by), (true, completed_by.opponent())] {
+                let mut board = Board { misere, ..Board::create_board(3) };
+                for &m in moves {
+                    board.make_move(m, 9).unwrap();
+                }
+                assert_eq!(board.get_result(), GameResult::Player(winner));
+            }
+        }
+    }
+
+    #[test]
+    fn torus_lines_are_kept_once() {
+        // With the whole period as the length every start on a line gives the same line
+        assert_eq!(torus_lines(3, 3, 3).len(), 12);
+        assert_eq!(torus_lines(4, 4, 3).len(), 64);
+        for line in torus_lines(4, 4, 4) {
+            assert_eq!(line.iter().collect::<HashSet<_>>().len(), 4);
+        }
+        assert_eq!(torus_lines(4, 4, 4).len(), 16);
+    }
+
+    #[test]
+    fn torus_wins_wrap_around() {
+        let mut board = Board { torus: true, ..Board::create_board(3) };
+        assert!(!board.square_layout());
+        for field in [1, 5, 6] {
+            board.fields[field] = Field::Player(Player::X);
+        }
+        assert_eq!(board.get_result(), GameResult::Player(Player::X));
+        board.torus = false;
+        assert_eq!(board.get_result(), GameResult::InProgress);
+    }
+
+    #[test]
+    fn overlines_by_rule_set() {
+        for (rules, x_wins) in [(RuleSet::FreeStyle, true), (RuleSet::Standard, false), (RuleSet::Renju, false)] {
+            let mut board = Board::create_gomoku_board(15, rules);
+            for field in 0..6 {
+                board.fields[field] = Field::Player(Player::X);
+            }
+            assert_eq!(board.get_result() == GameResult::Player(Player::X), x_wins, "{:?}", rules);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use crate::rules::GameRules;
+use crate::{GameResult, Player};
+use rand::Rng;
+
+struct Node<M> {
+    // Player who made the move leading to this node, wins are counted for them
+    mover: Player,
+    visits: u32,
+    wins: f64,
+    children: Vec<(M, usize)>,
+    untried: Vec<M>,
+}
+
+fn new_node<G: GameRules>(board: &G, mover: Player) -> Node<G::Move> {
+    let untried = if board.result() == GameResult::InProgress {
+        board.legal_moves()
+    } else {
+        vec![]
+    };
+    Node {
+        mover,
+        visits: 0,
+        wins: 0.0,
+        children: vec![],
+        untried,
+    }
+}
+
+// Plain UCT: select by the UCB1 score, expand one random untried move, finish the
+// game with random moves and back the result up. Returns the most visited move.
+pub fn mcts_best_move<G: GameRules>(board: &G, iterations: u32, exploration: f64) -> G::Move {
+    let mut rng = rand::thread_rng();
+    let mut tree = vec![new_node(board, board.side_to_move().opponent())];
+    for _ in 0..iterations {
+        let mut state = board.clone();
+        let mut path = vec![0];
+        let mut node = 0;
+        while tree[node].untried.is_empty() && !tree[node].children.is_empty() {
+            let log_visits = (tree[node].visits as f64).ln();
+            let mut best = tree[node].children[0];
+            let mut best_score = f64::MIN;
+            for &(m, child) in &tree[node].children {
+                let c = &tree[child];
+                let score = c.wins / c.visits as f64 + exploration * (log_visits / c.visits as f64).sqrt();
+                if score > best_score {
+                    best_score = score;
+                    best = (m, child);
+                }
+            }
+            state.apply(best.0).unwrap();
+            node = best.1;
+            path.push(node);
+        }
+
+        if !tree[node].untried.is_empty() {
+            let pick = rng.gen_range(0..tree[node].untried.len());
+            let m = tree[node].untried.swap_remove(pick);
+            let mover = state.side_to_move();
+            state.apply(m).unwrap();
+            tree.push(new_node(&state, mover));
+            let child = tree.len() - 1;
+            tree[node].children.push((m, child));
+            node = child;
+            path.push(node);
+        }
+
+        while state.result() == GameResult::InProgress {
+            let moves = state.legal_moves();
+            state.apply(moves[rng.gen_range(0..moves.len())]).unwrap();
+        }
+        let result = state.result();
+        for &n in &path {
+            tree[n].visits += 1;
+            tree[n].wins += match result {
+                GameResult::Player(winner) if winner == tree[n].mover => 1.0,
+                GameResult::Player(_) => 0.0,
+                _ => 0.5,
+            };
+        }
+    }
+
+    let root = &tree[0];
+    let mut best = root.children[0];
+    for &(m, child) in &root.children {
+        if tree[child].visits > tree[best.1].visits {
+            best = (m, child);
+        }
+    }
+    best.0
+}
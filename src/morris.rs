@@ -0,0 +1,368 @@
+use crate::retrograde::{better, Entry};
+use crate::rules::GameRules;
+use crate::{Board, Field, GameResult, Player};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MorrisMove {
+    Place(u32),
+    // From a piece of the mover to a free field next to it
+    Slide(u32, u32),
+}
+
+// Three Men's Morris (3 pieces each) and Achi (4 pieces) on a tic-tac-toe board: the
+// pieces are placed like marks, then with all of them down a turn moves one piece
+// along a line to the next free field, and a player who can't move loses. Sliding can
+// go round in circles, so a position that comes back `repetitions` times or reaching
+// `move_limit` plies is a draw.
+#[derive(Clone)]
+pub struct Morris {
+    pub board: Board,
+    pub pieces: usize,
+    pub repetitions: usize,
+    pub move_limit: usize,
+    // Fields next to every field along the lines of the board, shared between clones
+    adjacency: Rc<Vec<Vec<usize>>>,
+    moves: Vec<MorrisMove>,
+    // Fields before every move, for repetitions and for undoing slides
+    history: Vec<Vec<Field>>,
+}
+
+// Only the position counts, so lookup tables share transpositions. Anything stored in
+// them must not depend on the way there, which repetition draws do: solve works the
+// values out backwards without any history, alpha_beta_depth keeps no table.
+impl PartialEq for Morris {
+    fn eq(&self, other: &Self) -> bool {
+        self.board.fields == other.board.fields && self.board.player_turn == other.board.player_turn
+    }
+}
+
+impl Eq for Morris {}
+
+impl Hash for Morris {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.board.fields.hash(state);
+        self.board.player_turn.hash(state);
+    }
+}
+
+// Neighbours along the winning lines, for a 3x3 board the rows, the columns and both
+// diagonals through the center
+pub fn adjacency(board: &Board) -> Vec<Vec<usize>> {
+    let mut result = vec![vec![]; board.fields.len()];
+    for line in board.winning_lines() {
+        for pair in line.windows(2) {
+            if !result[pair[0]].contains(&pair[1]) {
+                result[pair[0]].push(pair[1]);
+                result[pair[1]].push(pair[0]);
+            }
+        }
+    }
+    result
+}
+
+impl Morris {
+    pub fn create_game(n: u32, pieces: usize) -> Self {
+        let board = Board::create_board(n);
+        Self {
+            adjacency: Rc::new(adjacency(&board)),
+            board,
+            pieces,
+            repetitions: 3,
+            move_limit: 200,
+            moves: vec![],
+            history: vec![],
+        }
+    }
+
+    // Board::moves only holds the placements
+    pub fn placing(&self) -> bool {
+        self.board.moves.len() < 2 * self.pieces
+    }
+
+    // How often the current position was on the board, this time included
+    pub fn occurrences(&self) -> usize {
+        let ply = self.history.len();
+        1 + (ply % 2..ply).step_by(2).filter(|&earlier| self.history[earlier] == self.board.fields).count()
+    }
+
+    // The result by the rules alone, without repetitions and the move limit
+    fn position_result(&self) -> GameResult {
+        match self.board.get_result() {
+            GameResult::InProgress if !self.placing() && self.legal_moves().is_empty() => {
+                GameResult::Player(self.board.player_turn.opponent())
+            }
+            result => result,
+        }
+    }
+}
+
+impl GameRules for Morris {
+    type Move = MorrisMove;
+
+    fn legal_moves(&self) -> Vec<MorrisMove> {
+        if self.placing() {
+            return self.board.free_moves().into_iter().map(MorrisMove::Place).collect();
+        }
+        let own = Field::Player(self.board.player_turn);
+        let mut moves = vec![];
+        for from in (0..self.board.fields.len()).filter(|&f| self.board.fields[f] == own) {
+            for &to in self.adjacency[from].iter().filter(|&&t| self.board.fields[t] == Field::Free) {
+                moves.push(MorrisMove::Slide(from as u32, to as u32));
+            }
+        }
+        moves
+    }
+
+    fn apply(&mut self, m: MorrisMove) -> Result<(), &'static str> {
+        let fields = self.board.fields.clone();
+        match m {
+            MorrisMove::Place(_) if !self.placing() => return Err("All pieces are placed, slide one"),
+            MorrisMove::Place(field) => self.board.make_move(field, self.board.size * self.board.size)?,
+            MorrisMove::Slide(..) if self.placing() => return Err("Pieces only slide once all are placed"),
+            MorrisMove::Slide(from, to) => {
+                let (from, to) = (from as usize, to as usize);
+                if self.board.fields.get(from) != Some(&Field::Player(self.board.player_turn)) {
+                    return Err("No piece of yours there");
+                }
+                if !self.adjacency[from].contains(&to) {
+                    return Err("Fields are not next to each other");
+                }
+                if self.board.fields[to] != Field::Free {
+                    return Err("Field is already taken!");
+                }
+                self.board.fields[to] = self.board.fields[from];
+                self.board.fields[from] = Field::Free;
+                self.board.player_turn = self.board.player_turn.opponent();
+            }
+        }
+        self.history.push(fields);
+        self.moves.push(m);
+        Ok(())
+    }
+
+    fn undo(&mut self) -> Result<(), &'static str> {
+        match self.moves.pop().ok_or("No moves has been played")? {
+            MorrisMove::Place(_) => self.board.undo_last_move()?,
+            MorrisMove::Slide(..) => {
+                self.board.fields = self.history.last().unwrap().clone();
+                self.board.player_turn = self.board.player_turn.opponent();
+            }
+        }
+        self.history.pop();
+        Ok(())
+    }
+
+    fn result(&self) -> GameResult {
+        match self.position_result() {
+            GameResult::InProgress
+                if self.occurrences() >= self.repetitions || self.moves.len() >= self.move_limit =>
+            {
+                GameResult::Draw
+            }
+            result => result,
+        }
+    }
+
+    fn side_to_move(&self) -> Player {
+        self.board.player_turn
+    }
+}
+
+// Retrograde analysis of every position reachable from `start`. Sliding makes the
+// positions a graph with cycles instead of layers, so the values spread backwards
+// from the finished games: a position is won once one child is lost for the opponent,
+// lost once all children are won for them. What never gets a value is a draw, neither
+// side can force anything there, which is what repetition draws come down to.
+pub fn solve(start: &Morris) -> HashMap<Morris, Entry> {
+    let root = Morris {
+        moves: vec![],
+        history: vec![],
+        ..start.clone()
+    };
+    let mut index = HashMap::from([(root.clone(), 0)]);
+    let mut positions = vec![root];
+    let mut parents: Vec<Vec<usize>> = vec![vec![]];
+    let mut open_children = vec![];
+    let mut next = 0;
+    while next < positions.len() {
+        let position = positions[next].clone();
+        let mut children = 0;
+        if position.position_result() == GameResult::InProgress {
+            for m in position.legal_moves() {
+                let mut child = position.clone();
+                child.apply(m).unwrap();
+                child.moves = vec![];
+                child.history = vec![];
+                let child_index = *index.entry(child.clone()).or_insert_with(|| {
+                    positions.push(child);
+                    parents.push(vec![]);
+                    positions.len() - 1
+                });
+                if !parents[child_index].contains(&next) {
+                    parents[child_index].push(next);
+                    children += 1;
+                }
+            }
+        }
+        open_children.push(children);
+        next += 1;
+    }
+
+    let mut entries: Vec<Option<Entry>> = vec![None; positions.len()];
+    let mut queue = VecDeque::new();
+    for (i, position) in positions.iter().enumerate() {
+        let result = position.position_result();
+        if result != GameResult::InProgress {
+            entries[i] = Some(Entry { result, distance: 0 });
+            queue.push_back(i);
+        }
+    }
+    while let Some(child) = queue.pop_front() {
+        let entry = entries[child].unwrap();
+        for &parent in &parents[child] {
+            if entries[parent].is_some() {
+                continue;
+            }
+            let mover = positions[parent].board.player_turn;
+            open_children[parent] -= 1;
+            let won = entry.result == GameResult::Player(mover);
+            let lost = entry.result == GameResult::Player(mover.opponent()) && open_children[parent] == 0;
+            if won || lost {
+                entries[parent] = Some(Entry {
+                    result: entry.result,
+                    distance: entry.distance.saturating_add(1),
+                });
+                queue.push_back(parent);
+            }
+        }
+    }
+
+    positions
+        .into_iter()
+        .zip(entries)
+        .map(|(position, entry)| {
+            let entry = entry.unwrap_or(Entry {
+                result: GameResult::Draw,
+                distance: 0,
+            });
+            (position, entry)
+        })
+        .collect()
+}
+
+// Positions whose entry doesn't follow from their children: a win needs a won child,
+// the nearest one, a loss needs every child lost, the farthest one, and a draw neither
+pub fn verify(table: &HashMap<Morris, Entry>) -> usize {
+    let mut mismatches = 0;
+    for (position, entry) in table {
+        if position.position_result() != GameResult::InProgress {
+            continue;
+        }
+        let mover = position.board.player_turn;
+        let children: Vec<Entry> = position
+            .legal_moves()
+            .into_iter()
+            .map(|m| {
+                let mut child = position.clone();
+                child.apply(m).unwrap();
+                table[&child]
+            })
+            .collect();
+        let won = children.iter().filter(|c| c.result == GameResult::Player(mover)).map(|c| c.distance).min();
+        let lost = children.iter().all(|c| c.result == GameResult::Player(mover.opponent()));
+        let expected = match (won, lost) {
+            (Some(distance), _) => Entry {
+                result: GameResult::Player(mover),
+                distance: distance + 1,
+            },
+            (None, true) => Entry {
+                result: GameResult::Player(mover.opponent()),
+                distance: children.iter().map(|c| c.distance).max().unwrap() + 1,
+            },
+            (None, false) => Entry {
+                result: GameResult::Draw,
+                distance: 0,
+            },
+        };
+        if *entry != expected {
+            mismatches += 1;
+        }
+    }
+    mismatches
+}
+
+// The winner hurries, the loser stalls, as in retrograde::Database::best_move
+pub fn best_move(table: &HashMap<Morris, Entry>, game: &Morris) -> MorrisMove {
+    let mut best: Option<(MorrisMove, Entry)> = None;
+    for m in game.legal_moves() {
+        let mut child = game.clone();
+        child.apply(m).unwrap();
+        let entry = table[&child];
+        if best.is_none_or(|(_, b)| better(game.board.player_turn, entry, b)) {
+            best = Some((m, entry));
+        }
+    }
+    best.unwrap().0
+}
+
+// Move picking player, for matches between the AIs
+pub type Engine<'a> = &'a dyn Fn(&Morris) -> MorrisMove;
+
+// Open lines, plus how free the pieces are to move once sliding
+pub fn morris_eval(game: &Morris) -> i32 {
+    let mut result = game.board.lines_heuristic(Player::X);
+    if !game.placing() {
+        for (field, neighbours) in game.adjacency.iter().enumerate() {
+            let free = neighbours.iter().filter(|&&n| game.board.fields[n] == Field::Free).count() as i32;
+            match game.board.fields[field] {
+                Field::Player(Player::X) => result += free,
+                Field::Player(Player::O) => result -= free,
+                Field::Free => {}
+            }
+        }
+    }
+    result
+}
+
+impl fmt::Debug for Morris {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.board)?;
+        if self.placing() {
+            let left = self.pieces - self.board.moves.len() / 2;
+            write!(f, "{:?} to move, placing ({} left)", self.board.player_turn, left)
+        } else {
+            write!(f, "{:?} to move, sliding", self.board.player_turn)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::play_out;
+    use crate::rules::tests::undo_round_trip;
+
+    #[test]
+    fn solved_tables_follow_from_their_children() {
+        for (pieces, distance) in [(3, 9), (4, 11)] {
+            let game = Morris::create_game(3, pieces);
+            let table = solve(&game);
+            assert_eq!(verify(&table), 0);
+            assert_eq!(table[&game], Entry { result: GameResult::Player(Player::X), distance });
+            let perfect = |g: &Morris| best_move(&table, g);
+            let end = play_out(game, &perfect, &perfect);
+            assert_eq!(end.result(), GameResult::Player(Player::X));
+            assert_eq!(end.moves.len(), distance as usize);
+        }
+    }
+
+    #[test]
+    fn undo_restores_slides() {
+        let game = Morris::create_game(3, 3);
+        undo_round_trip(&game, 100, |g| (g.board.fields.clone(), g.board.player_turn));
+    }
+}
@@ -0,0 +1,414 @@
+use crate::ranking::symmetries;
+use crate::rules::GameRules;
+use crate::{lines, Board, Field, GameResult, Player};
+use rand::Rng;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+// Notakto: both players place X on any of several 3x3 boards, a board with three in a
+// row is dead and takes no more moves, whoever kills the last board loses.
+#[derive(Clone)]
+pub struct Notakto {
+    pub boards: Vec<Board>,
+    player_turn: Player,
+    // (board, field) of every move
+    moves: Vec<(usize, u32)>,
+}
+
+// Both players place the same symbol, so the stone count decides whose turn it is
+impl PartialEq for Notakto {
+    fn eq(&self, other: &Self) -> bool {
+        self.boards == other.boards
+    }
+}
+
+impl Eq for Notakto {}
+
+impl Hash for Notakto {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.boards.hash(state);
+    }
+}
+
+impl Notakto {
+    pub fn create_game(boards: usize) -> Self {
+        Self {
+            boards: vec![Board::create_board(3); boards],
+            player_turn: Player::X,
+            moves: vec![],
+        }
+    }
+
+    // Every board gets a few random stones, never three in a row
+    pub fn random_position(boards: usize) -> Self {
+        let mut rng = rand::thread_rng();
+        let mut game = Self::create_game(boards);
+        for board in 0..boards {
+            for _ in 0..rng.gen_range(0..6) {
+                let field = rng.gen_range(0..9);
+                let mut next = game.clone();
+                if next.apply((board, field)).is_ok() && next.boards[board].get_result() == GameResult::InProgress {
+                    game = next;
+                }
+            }
+        }
+        game.moves = vec![];
+        game
+    }
+
+    fn is_dead(board: &Board) -> bool {
+        board.get_result() == GameResult::Player(Player::X)
+    }
+
+    // Boards still in play as bit masks of their X fields, the form the solver works on
+    pub fn masks(&self) -> Vec<u16> {
+        self.boards
+            .iter()
+            .filter(|b| !Self::is_dead(b))
+            .map(|b| {
+                b.fields
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &f)| f != Field::Free)
+                    .map(|(i, _)| 1 << i)
+                    .sum()
+            })
+            .collect()
+    }
+}
+
+impl GameRules for Notakto {
+    type Move = (usize, u32);
+
+    fn legal_moves(&self) -> Vec<(usize, u32)> {
+        self.boards
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| !Self::is_dead(b))
+            .flat_map(|(index, b)| b.free_moves().into_iter().map(move |f| (index, f)))
+            .collect()
+    }
+
+    fn apply(&mut self, (board, field): (usize, u32)) -> Result<(), &'static str> {
+        let target = self.boards.get_mut(board).ok_or("Wrong board number")?;
+        if Self::is_dead(target) {
+            return Err("Board is already dead!");
+        }
+        target.player_turn = Player::X;
+        target.make_move(field, 9)?;
+        self.moves.push((board, field));
+        self.player_turn = self.player_turn.opponent();
+        Ok(())
+    }
+
+    fn undo(&mut self) -> Result<(), &'static str> {
+        let &(board, _) = self.moves.last().ok_or("No moves has been played")?;
+        self.boards[board].undo_last_move()?;
+        self.moves.pop();
+        self.player_turn = self.player_turn.opponent();
+        Ok(())
+    }
+
+    // The player who killed the last board has lost, so the one to move has won
+    fn result(&self) -> GameResult {
+        if self.boards.iter().all(Self::is_dead) {
+            GameResult::Player(self.player_turn)
+        } else {
+            GameResult::InProgress
+        }
+    }
+
+    fn side_to_move(&self) -> Player {
+        self.player_turn
+    }
+}
+
+impl fmt::Debug for Notakto {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (index, board) in self.boards.iter().enumerate() {
+            let state = if Self::is_dead(board) { " (dead)" } else { "" };
+            write!(f, "Board {}{}:{:?}", index, state, board)?;
+        }
+        write!(f, "{:?} to move", self.player_turn)
+    }
+}
+
+// Element a^i b^j c^k d^l of the misere quotient of 3x3 Notakto found by Plambeck and
+// Whitman, Q = <a, b, c, d | a² = 1, b³ = b, b²c = c, c³ = ac², b²d = d, cd = ad, d² = c²>.
+// A sum of boards is lost for the player to move iff the product of their values is
+// a, b², bc or c².
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Quotient([u8; 4]);
+
+impl Quotient {
+    pub const ONE: Quotient = Quotient([0; 4]);
+
+    fn normalize(self) -> Self {
+        let [mut a, mut b, mut c, mut d] = self.0;
+        loop {
+            let before = [a, b, c, d];
+            if b >= 3 {
+                b -= 2;
+            }
+            if b == 2 && (c > 0 || d > 0) {
+                b = 0;
+            }
+            if c >= 3 {
+                c -= 1;
+                a += 1;
+            }
+            if c > 0 && d > 0 {
+                c -= 1;
+                a += 1;
+            }
+            if d >= 2 {
+                d -= 2;
+                c += 2;
+            }
+            a %= 2;
+            if [a, b, c, d] == before {
+                return Quotient([a, b, c, d]);
+            }
+        }
+    }
+
+    // From the written form, "ab²" and the like, "1" for the identity
+    fn parse(name: &str) -> Quotient {
+        let mut exponents = [0; 4];
+        let mut last = 0;
+        for symbol in name.chars() {
+            match symbol {
+                'a'..='d' => {
+                    last = symbol as usize - 'a' as usize;
+                    exponents[last] = 1;
+                }
+                '²' => exponents[last] = 2,
+                _ => {}
+            }
+        }
+        Quotient(exponents).normalize()
+    }
+
+    // Multiplying adds the exponents
+    pub fn times(self, other: Quotient) -> Quotient {
+        let mut exponents = self.0;
+        for (e, o) in exponents.iter_mut().zip(other.0) {
+            *e += o;
+        }
+        Quotient(exponents).normalize()
+    }
+
+    pub fn is_p_position(self) -> bool {
+        matches!(self.0, [1, 0, 0, 0] | [0, 2, 0, 0] | [0, 1, 1, 0] | [0, 0, 2, 0])
+    }
+}
+
+impl fmt::Display for Quotient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if *self == Self::ONE {
+            return write!(f, "1");
+        }
+        for (name, &exponent) in ["a", "b", "c", "d"].iter().zip(&self.0) {
+            match exponent {
+                0 => {}
+                1 => write!(f, "{}", name)?,
+                _ => write!(f, "{}²", name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// Values of the 46 live 3x3 boards up to symmetry, as published by Plambeck and
+// Whitman, keyed by the mask canonical() gives them. Fields are numbered row by row
+// from the top left, bit i is field i.
+const VALUES: [(u16, &str); 46] = [
+    (0x000, "c"), // ... ... ...
+    (0x001, "1"), // X.. ... ...
+    (0x002, "1"), // .X. ... ...
+    (0x010, "c²"), // ... .X. ...
+    (0x003, "ad"), // XX. ... ...
+    (0x005, "b"), // X.X ... ...
+    (0x00a, "a"), // .X. X.. ...
+    (0x00c, "b"), // ..X X.. ...
+    (0x011, "b"), // X.. .X. ...
+    (0x012, "b"), // .X. .X. ...
+    (0x028, "a"), // ... X.X ...
+    (0x044, "a"), // ..X ... X..
+    (0x00b, "b"), // XX. X.. ...
+    (0x00d, "a"), // X.X X.. ...
+    (0x00e, "d"), // .XX X.. ...
+    (0x013, "ab"), // XX. .X. ...
+    (0x015, "a"), // X.X .X. ...
+    (0x01a, "ab"), // .X. XX. ...
+    (0x01c, "a"), // ..X XX. ...
+    (0x029, "d"), // X.. X.X ...
+    (0x02a, "b"), // .X. X.X ...
+    (0x045, "ab"), // X.X ... X..
+    (0x046, "d"), // .XX ... X..
+    (0x061, "a"), // X.. ..X X..
+    (0x062, "1"), // .X. ..X X..
+    (0x01b, "a"), // XX. XX. ...
+    (0x01d, "b"), // X.X XX. ...
+    (0x01e, "b"), // .XX XX. ...
+    (0x02b, "a"), // XX. X.X ...
+    (0x02d, "b"), // X.X X.X ...
+    (0x04e, "ab"), // .XX X.. X..
+    (0x063, "b"), // XX. ..X X..
+    (0x065, "b"), // X.X ..X X..
+    (0x066, "a"), // .XX ..X X..
+    (0x06a, "ab"), // .X. X.X X..
+    (0x06c, "a"), // ..X X.X X..
+    (0x071, "b"), // X.. .XX X..
+    (0x072, "b"), // .X. .XX X..
+    (0x0aa, "a"), // .X. X.X .X.
+    (0x145, "a"), // X.X ... X.X
+    (0x06e, "b"), // .XX X.X X..
+    (0x073, "a"), // XX. .XX X..
+    (0x0ab, "b"), // XX. X.X .X.
+    (0x0ad, "a"), // X.X X.X .X.
+    (0x0e5, "a"), // X.X ..X XX.
+    (0x0ee, "a"), // .XX X.X XX.
+];
+
+// Misere solver working on sums of boards given as bit masks, through the published
+// quotient values. brute_force_lost searches the game tree instead, as an independent
+// check of the values.
+pub struct Solver {
+    lines: Vec<Vec<usize>>,
+    symmetries: Vec<Vec<usize>>,
+    // Brute force results, keyed by sorted canonical masks: is the player to move lost?
+    outcomes: HashMap<Vec<u16>, bool>,
+    values: HashMap<u16, Quotient>,
+}
+
+impl Solver {
+    pub fn new() -> Self {
+        Self {
+            lines: lines(3, 3, 3),
+            symmetries: symmetries(3),
+            outcomes: HashMap::new(),
+            values: VALUES.iter().map(|&(mask, name)| (mask, Quotient::parse(name))).collect(),
+        }
+    }
+
+    fn dead(&self, mask: u16) -> bool {
+        self.lines.iter().any(|l| l.iter().all(|&f| mask & (1 << f) != 0))
+    }
+
+    fn canonical(&self, mask: u16) -> u16 {
+        self.symmetries
+            .iter()
+            .map(|s| (0..9).filter(|&f| mask & (1 << s[f]) != 0).map(|f| 1 << f).sum())
+            .min()
+            .unwrap()
+    }
+
+    // Plain game tree search: lost iff every move leads to a position won for the opponent
+    pub fn brute_force_lost(&mut self, boards: &[u16]) -> bool {
+        let mut key: Vec<u16> = boards.iter().map(|&m| self.canonical(m)).collect();
+        key.sort();
+        if let Some(&lost) = self.outcomes.get(&key) {
+            return lost;
+        }
+        // With every board dead the opponent made the last move and lost
+        let mut lost = !key.is_empty();
+        'search: for index in 0..key.len() {
+            for field in (0..9).filter(|&f| key[index] & (1 << f) == 0) {
+                let mut next = key.clone();
+                next[index] |= 1 << field;
+                if self.dead(next[index]) {
+                    next.remove(index);
+                }
+                if self.brute_force_lost(&next) {
+                    lost = false;
+                    break 'search;
+                }
+            }
+        }
+        self.outcomes.insert(key, lost);
+        lost
+    }
+
+    pub fn value(&self, mask: u16) -> Quotient {
+        if self.dead(mask) {
+            return Quotient::ONE;
+        }
+        self.values[&self.canonical(mask)]
+    }
+
+    pub fn board_classes(&self) -> usize {
+        self.values.len()
+    }
+
+    // Sums of up to three live boards, up to symmetry, where the values and brute force
+    // disagree
+    pub fn check(&mut self) -> usize {
+        let classes: Vec<u16> = VALUES.iter().map(|&(mask, _)| mask).collect();
+        let mut mismatches = 0;
+        for (i, &x) in classes.iter().enumerate() {
+            for (j, &y) in classes.iter().enumerate().skip(i) {
+                for &z in &classes[j..] {
+                    for sum in [vec![x], vec![x, y], vec![x, y, z]] {
+                        if self.lost(&sum) != self.brute_force_lost(&sum) {
+                            mismatches += 1;
+                        }
+                    }
+                }
+            }
+        }
+        mismatches
+    }
+
+    pub fn lost(&self, boards: &[u16]) -> bool {
+        boards.iter().fold(Quotient::ONE, |q, &m| q.times(self.value(m))).is_p_position()
+    }
+
+    // A move that leaves the opponent lost, or any move when there is none
+    pub fn best_move(&self, game: &Notakto) -> (usize, u32) {
+        let moves = game.legal_moves();
+        for &m in &moves {
+            let mut next = game.clone();
+            next.apply(m).unwrap();
+            if self.lost(&next.masks()) {
+                return m;
+            }
+        }
+        moves[0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn published_values_agree_with_brute_force() {
+        let mut solver = Solver::new();
+        assert_eq!(solver.board_classes(), 46);
+        assert_eq!(solver.check(), 0);
+        for _ in 0..10 {
+            let masks = Notakto::random_position(4).masks();
+            assert_eq!(solver.lost(&masks), solver.brute_force_lost(&masks));
+        }
+    }
+
+    // First player wins on one board, second on two, first again on three
+    #[test]
+    fn empty_boards() {
+        let solver = Solver::new();
+        assert_eq!(solver.value(0), Quotient::parse("c"));
+        assert!(!solver.lost(&[0]));
+        assert!(solver.lost(&[0, 0]));
+        assert!(!solver.lost(&[0, 0, 0]));
+    }
+
+    #[test]
+    fn best_move_leaves_a_lost_position() {
+        let solver = Solver::new();
+        let mut game = Notakto::create_game(3);
+        game.apply(solver.best_move(&game)).unwrap();
+        assert!(solver.lost(&game.masks()));
+    }
+}
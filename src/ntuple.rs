@@ -0,0 +1,158 @@
+use crate::{lines, Board, Field, GameResult, Player};
+use rand::Rng;
+use std::fs;
+use std::io;
+
+// N-tuple network evaluator. Every straight run of `tuple_length` fields is a tuple,
+// and the contents of its fields (free, X, O) index that tuple's weight table of
+// 3^tuple_length entries. The value of a board is tanh of the sum of the selected
+// weights, from X's point of view like in qlearning.
+pub struct NTuple {
+    size: u32,
+    win_length: u32,
+    tuple_length: u32,
+    tuples: Vec<Vec<usize>>,
+    weights: Vec<Vec<f64>>,
+    pub learning_rate: f64,
+    pub exploration: f64,
+    pub episodes: u32,
+}
+
+impl NTuple {
+    pub fn new(
+        size: u32,
+        win_length: u32,
+        tuple_length: u32,
+        learning_rate: f64,
+        exploration: f64,
+        episodes: u32,
+    ) -> Self {
+        let tuples = lines(size, size, tuple_length);
+        let weights = vec![vec![0.0; 3usize.pow(tuple_length)]; tuples.len()];
+        Self {
+            size,
+            win_length,
+            tuple_length,
+            tuples,
+            weights,
+            learning_rate,
+            exploration,
+            episodes,
+        }
+    }
+
+    fn index(&self, tuple: &[usize], board: &Board) -> usize {
+        tuple.iter().fold(0, |acc, &field| {
+            acc * 3
+                + match board.fields[field] {
+                    Field::Free => 0,
+                    Field::Player(Player::X) => 1,
+                    Field::Player(Player::O) => 2,
+                }
+        })
+    }
+
+    fn network(&self, board: &Board) -> f64 {
+        let sum: f64 = self
+            .tuples
+            .iter()
+            .zip(&self.weights)
+            .map(|(tuple, weights)| weights[self.index(tuple, board)])
+            .sum();
+        sum.tanh()
+    }
+
+    pub fn value(&self, board: &Board) -> f64 {
+        match board.get_result() {
+            GameResult::Player(Player::X) => 1.0,
+            GameResult::Player(Player::O) => -1.0,
+            GameResult::Draw => 0.0,
+            GameResult::InProgress => self.network(board),
+        }
+    }
+
+    // Horizon evaluation for alpha_beta_depth, on the same scale as lines_heuristic
+    // would be for a handful of lines.
+    pub fn eval(&self, board: &Board) -> i32 {
+        (self.network(board) * 1000.0) as i32
+    }
+
+    fn update(&mut self, board: &Board, target: f64) {
+        let value = self.network(board);
+        let step = self.learning_rate * (target - value) * (1.0 - value * value);
+        for t in 0..self.tuples.len() {
+            let index = self.index(&self.tuples[t], board);
+            self.weights[t][index] += step;
+        }
+    }
+
+    pub fn train(&mut self) {
+        let mut rng = rand::thread_rng();
+        for _ in 0..self.episodes {
+            let mut board = Board::create_board_k(self.size, self.win_length);
+            let mut previous: Option<Board> = None;
+            while board.get_result() == GameResult::InProgress {
+                let moves = board.generate_moves();
+                let explore = rng.gen::<f64>() < self.exploration;
+                let chosen = if explore {
+                    rng.gen_range(0..moves.len())
+                } else {
+                    let values: Vec<f64> = moves.iter().map(|b| self.value(b)).collect();
+                    let mut best = 0;
+                    for index in 1..values.len() {
+                        let better = match board.player_turn {
+                            Player::X => values[index] > values[best],
+                            Player::O => values[index] < values[best],
+                        };
+                        if better {
+                            best = index;
+                        }
+                    }
+                    best
+                };
+                board = moves[chosen].clone();
+                if let (false, Some(prev)) = (explore, &previous) {
+                    let target = self.value(&board);
+                    self.update(prev, target);
+                }
+                previous = Some(board.clone());
+            }
+        }
+    }
+
+    // File format: "size win_length tuple_length" on the first line, then the
+    // weights of one tuple per line.
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut out = format!("{} {} {}\n", self.size, self.win_length, self.tuple_length);
+        for weights in &self.weights {
+            let line: Vec<String> = weights.iter().map(|w| w.to_string()).collect();
+            out.push_str(&line.join(" "));
+            out.push('\n');
+        }
+        fs::write(path, out)
+    }
+
+    pub fn load(&mut self, path: &str) -> io::Result<()> {
+        let bad_data = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+        let content = fs::read_to_string(path)?;
+        let mut lines = content.lines();
+        let header = lines.next().ok_or_else(|| bad_data("Missing header"))?;
+        if header != format!("{} {} {}", self.size, self.win_length, self.tuple_length) {
+            return Err(bad_data("Network was trained for a different board"));
+        }
+        let mut weights = vec![];
+        for line in lines {
+            let row: Result<Vec<f64>, _> = line.split(' ').map(|w| w.parse()).collect();
+            let row = row.map_err(|_| bad_data("Malformed weight"))?;
+            if row.len() != 3usize.pow(self.tuple_length) {
+                return Err(bad_data("Wrong number of weights"));
+            }
+            weights.push(row);
+        }
+        if weights.len() != self.tuples.len() {
+            return Err(bad_data("Wrong number of tuples"));
+        }
+        self.weights = weights;
+        Ok(())
+    }
+}
@@ -0,0 +1,204 @@
+use crate::rules::GameRules;
+use crate::wild::WildMove;
+use crate::{Board, Field, GameResult, Player};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Role {
+    // Wins with five in a row of either symbol
+    Order,
+    // Wins when the board fills up without that
+    Chaos,
+}
+
+// Order and Chaos on 6x6: both players place X or O, Order moves first. The roles
+// decide who wins, the symbols only form the lines, so the result is worked out per
+// role and then given to the player holding it.
+#[derive(Clone)]
+pub struct OrderChaos {
+    pub board: Board,
+    // Player holding the Order role, the other one plays Chaos
+    pub order: Player,
+    player_turn: Player,
+    moves: Vec<WildMove>,
+}
+
+// The stone count decides whose turn it is
+impl PartialEq for OrderChaos {
+    fn eq(&self, other: &Self) -> bool {
+        self.board == other.board && self.order == other.order
+    }
+}
+
+impl Eq for OrderChaos {}
+
+impl Hash for OrderChaos {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.board.hash(state);
+        self.order.hash(state);
+    }
+}
+
+impl OrderChaos {
+    pub fn create_game(order: Player) -> Self {
+        Self {
+            board: Board::create_board_k(6, 5),
+            order,
+            player_turn: order,
+            moves: vec![],
+        }
+    }
+
+    pub fn role(&self, player: Player) -> Role {
+        if player == self.order {
+            Role::Order
+        } else {
+            Role::Chaos
+        }
+    }
+
+    fn player(&self, role: Role) -> Player {
+        match role {
+            Role::Order => self.order,
+            Role::Chaos => self.order.opponent(),
+        }
+    }
+
+    pub fn winner(&self) -> Option<Role> {
+        match self.board.get_result() {
+            GameResult::Player(_) => Some(Role::Order),
+            GameResult::Draw => Some(Role::Chaos),
+            GameResult::InProgress => None,
+        }
+    }
+
+    // Does `m` complete a line of its symbol?
+    fn completes_line(&self, m: WildMove) -> bool {
+        let field = m.field as usize;
+        self.board
+            .winning_lines()
+            .iter()
+            .filter(|l| l.contains(&field))
+            .any(|l| l.iter().all(|&f| f == field || self.board.fields[f] == Field::Player(m.symbol)))
+    }
+}
+
+impl GameRules for OrderChaos {
+    type Move = WildMove;
+
+    fn legal_moves(&self) -> Vec<WildMove> {
+        self.board
+            .free_moves()
+            .into_iter()
+            .flat_map(|field| [Player::X, Player::O].map(|symbol| WildMove { field, symbol }))
+            .collect()
+    }
+
+    fn apply(&mut self, m: WildMove) -> Result<(), &'static str> {
+        self.board.player_turn = m.symbol;
+        self.board.make_move(m.field, self.board.size * self.board.size)?;
+        self.moves.push(m);
+        self.player_turn = self.player_turn.opponent();
+        Ok(())
+    }
+
+    fn undo(&mut self) -> Result<(), &'static str> {
+        self.board.undo_last_move()?;
+        self.moves.pop();
+        self.player_turn = self.player_turn.opponent();
+        Ok(())
+    }
+
+    fn result(&self) -> GameResult {
+        match self.winner() {
+            Some(role) => GameResult::Player(self.player(role)),
+            None => GameResult::InProgress,
+        }
+    }
+
+    fn side_to_move(&self) -> Player {
+        self.player_turn
+    }
+
+    // Order tries the moves that complete a line first, Chaos tries them last
+    fn ordered_moves(&self) -> Vec<WildMove> {
+        let (winning, other): (Vec<WildMove>, Vec<WildMove>) =
+            self.legal_moves().into_iter().partition(|&m| self.completes_line(m));
+        match self.role(self.player_turn) {
+            Role::Order => winning.into_iter().chain(other).collect(),
+            Role::Chaos => other.into_iter().chain(winning).collect(),
+        }
+    }
+}
+
+// Move picking player, for matches between the AIs
+pub type Engine<'a> = &'a dyn Fn(&OrderChaos) -> WildMove;
+
+// Lines that can still become five of one symbol, weighted by how far they are, from
+// the point of view of X like every eval
+pub fn order_chaos_eval(game: &OrderChaos) -> i32 {
+    let mut open = 0;
+    for line in game.board.winning_lines() {
+        let x = line.iter().filter(|&&f| game.board.fields[f] == Field::Player(Player::X)).count();
+        let o = line.iter().filter(|&&f| game.board.fields[f] == Field::Player(Player::O)).count();
+        if x == 0 || o == 0 {
+            let stones = (x + o) as i32;
+            open += 1 + stones * stones;
+        }
+    }
+    match game.order {
+        Player::X => open,
+        Player::O => -open,
+    }
+}
+
+impl fmt::Debug for OrderChaos {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.board)?;
+        write!(
+            f,
+            "Order: {:?}, Chaos: {:?}, {:?} to move",
+            self.order,
+            self.order.opponent(),
+            self.player_turn
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Five of either symbol in the top row wins for Order, whoever holds it
+    #[test]
+    fn five_in_a_row_wins_for_order() {
+        for order in [Player::X, Player::O] {
+            for symbol in [Player::X, Player::O] {
+                let mut game = OrderChaos::create_game(order);
+                for field in 0..5 {
+                    assert_eq!(game.result(), GameResult::InProgress);
+                    game.apply(WildMove { field, symbol }).unwrap();
+                    if field < 4 {
+                        game.apply(WildMove { field: 30 + field, symbol: symbol.opponent() }).unwrap();
+                    }
+                }
+                assert_eq!(game.winner(), Some(Role::Order));
+                assert_eq!(game.result(), GameResult::Player(order));
+            }
+        }
+    }
+
+    // Symbols in pairs along the rows and shifted every row leave no five anywhere
+    #[test]
+    fn full_board_without_five_wins_for_chaos() {
+        let mut game = OrderChaos::create_game(Player::X);
+        for field in 0..36 {
+            let (row, col) = (field / 6, field % 6);
+            let symbol = if (col / 2 + row) % 2 == 0 { Player::X } else { Player::O };
+            game.apply(WildMove { field, symbol }).unwrap();
+        }
+        assert_eq!(game.winner(), Some(Role::Chaos));
+        assert_eq!(game.result(), GameResult::Player(Player::O));
+    }
+}
@@ -0,0 +1,108 @@
+use crate::{Board, GameResult};
+
+// Leaf counts at every depth from the empty 3x3 board, finished games are not extended
+const PERFT_3X3: [u64; 10] = [1, 9, 72, 504, 3024, 15120, 54720, 148176, 200448, 127872];
+pub const GAMES_3X3: u64 = 255168;
+
+pub fn reference(board: &Board) -> Option<&'static [u64]> {
+    if board.size == 3 && board.win_length == 3 && !board.gravity && !board.torus && board.moves.is_empty() {
+        Some(&PERFT_3X3)
+    } else {
+        None
+    }
+}
+
+// Number of move sequences of exactly `depth` moves, using make_move/undo_last_move
+// and checking that every undo gives back the board it started from.
+pub fn perft(board: &mut Board, depth: u32) -> Result<u64, &'static str> {
+    if depth == 0 {
+        return Ok(1);
+    }
+    if board.get_result() != GameResult::InProgress {
+        return Ok(0);
+    }
+    let before = board.clone();
+    let mut nodes = 0;
+    for index in board.free_moves() {
+        board.make_move(index, board.size * board.size)?;
+        nodes += perft(board, depth - 1)?;
+        board.undo_last_move()?;
+        if board.fields != before.fields
+            || board.moves != before.moves
+            || board.player_turn != before.player_turn
+        {
+            return Err("Undo did not restore the board");
+        }
+    }
+    Ok(nodes)
+}
+
+// Same count with the cloning generate_moves, to cross check make/undo
+pub fn perft_clone(board: &Board, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    if board.get_result() != GameResult::InProgress {
+        return 0;
+    }
+    board
+        .generate_moves()
+        .iter()
+        .map(|b| perft_clone(b, depth - 1))
+        .sum()
+}
+
+// Number of different games that can be played to the end from `board`
+pub fn complete_games(board: &mut Board) -> Result<u64, &'static str> {
+    if board.get_result() != GameResult::InProgress {
+        return Ok(1);
+    }
+    let mut games = 0;
+    for index in board.free_moves() {
+        board.make_move(index, board.size * board.size)?;
+        games += complete_games(board)?;
+        board.undo_last_move()?;
+    }
+    Ok(games)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Field;
+
+    #[test]
+    fn perft_3x3() {
+        let mut board = Board::create_board(3);
+        let reference = reference(&board).unwrap();
+        for (depth, &expected) in reference.iter().enumerate() {
+            assert_eq!(perft(&mut board, depth as u32), Ok(expected));
+            assert_eq!(perft_clone(&board, depth as u32), expected);
+        }
+        assert_eq!(complete_games(&mut board), Ok(GAMES_3X3));
+    }
+
+    // From a position with moves played, undo must stop at them
+    #[test]
+    fn perft_after_moves() {
+        let mut board = Board::create_board(3);
+        board.make_move(4, 9).unwrap();
+        board.make_move(0, 9).unwrap();
+        for depth in 0..=7 {
+            assert_eq!(perft(&mut board, depth), Ok(perft_clone(&board, depth)));
+        }
+        assert_eq!(board.moves, [4, 0]);
+    }
+
+    #[test]
+    fn bad_moves_are_errors() {
+        let mut board = Board::create_board(3);
+        assert!(board.make_move(9, 9).is_err());
+        board.make_move(4, 9).unwrap();
+        assert!(board.make_move(4, 9).is_err());
+        // A move list out of step with the fields is reported, not a panic
+        board.fields[4] = Field::Free;
+        assert!(board.undo_last_move().is_err());
+        assert_eq!(board.moves, [4]);
+    }
+}
@@ -0,0 +1,103 @@
+use crate::{Board, GameResult};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+// Searches our answers to the opponent's replies while they are thinking.
+// The thread owns the lookup table and hands it back when pondering finishes,
+// so even answers it did not get to leave the table warm.
+pub struct Ponder {
+    stop: Arc<AtomicBool>,
+    answers: Arc<Mutex<HashMap<u32, u32>>>,
+    handle: JoinHandle<HashMap<Board, GameResult>>,
+}
+
+impl Ponder {
+    // `board` has the opponent to move. The reply we expect them to play is searched
+    // first, then all the others in board order.
+    pub fn start(board: Board, mut lookup: HashMap<Board, GameResult>) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let answers = Arc::new(Mutex::new(HashMap::new()));
+        let thread_stop = Arc::clone(&stop);
+        let thread_answers = Arc::clone(&answers);
+        let handle = thread::spawn(move || {
+            if board.get_result() != GameResult::InProgress {
+                return lookup;
+            }
+            let Some(predicted) = board.find_best_move_lookup_until(&mut lookup, &thread_stop) else {
+                return lookup;
+            };
+            let mut replies: Vec<u32> = board
+                .generate_moves()
+                .iter()
+                .map(|b| *b.moves.last().unwrap())
+                .filter(|&index| index != predicted)
+                .collect();
+            replies.insert(0, predicted);
+
+            for reply in replies {
+                let mut next = board.clone();
+                next.make_move(reply, next.size * next.size).unwrap();
+                if next.get_result() != GameResult::InProgress {
+                    continue;
+                }
+                match next.find_best_move_lookup_until(&mut lookup, &thread_stop) {
+                    Some(answer) => thread_answers.lock().unwrap().insert(reply, answer),
+                    None => break,
+                };
+            }
+            lookup
+        });
+        Self {
+            stop,
+            answers,
+            handle,
+        }
+    }
+
+    // Stops pondering, the search in progress gives up right away and leaves only the
+    // positions it finished in the table. Returns our answer to `reply` if it was
+    // already found (a ponder hit) together with the lookup table.
+    pub fn finish(self, reply: u32) -> (Option<u32>, HashMap<Board, GameResult>) {
+        self.stop.store(true, Ordering::Relaxed);
+        let lookup = self.handle.join().unwrap();
+        let answer = self.answers.lock().unwrap().get(&reply).copied();
+        (answer, lookup)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::retrograde::Database;
+    use std::time::{Duration, Instant};
+
+    // Finishing must not wait for the 4x4 game to be solved
+    #[test]
+    fn finish_interrupts_the_search() {
+        let mut board = Board::create_board(4);
+        board.make_move(0, 16).unwrap();
+        let pondering = Ponder::start(board, HashMap::new());
+        thread::sleep(Duration::from_millis(100));
+        let start_time = Instant::now();
+        let (answer, _) = pondering.finish(5);
+        assert!(start_time.elapsed() < Duration::from_secs(1));
+        assert_eq!(answer, None);
+    }
+
+    #[test]
+    fn interrupted_lookup_holds_exact_results() {
+        let database = Database::solve(Board::create_board(3));
+        let mut board = Board::create_board(3);
+        board.make_move(4, 9).unwrap();
+        for wait in [0, 1, 5, 20] {
+            let pondering = Ponder::start(board.clone(), HashMap::new());
+            thread::sleep(Duration::from_millis(wait));
+            let (_, lookup) = pondering.finish(0);
+            for (position, result) in &lookup {
+                assert_eq!(database.get(position).unwrap().result, *result);
+            }
+        }
+    }
+}
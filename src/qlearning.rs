@@ -0,0 +1,174 @@
+use crate::{Board, Field, GameResult, Player};
+use rand::Rng;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+
+// Tabular TD(0) learner over afterstates (the board right after a move).
+// Values are kept from X's point of view: 1.0 means X wins, -1.0 O wins and
+// 0.0 is a draw, so X picks the highest value and O the lowest, same as min_max.
+pub struct QLearning {
+    values: HashMap<Board, f64>,
+    size: u32,
+    pub learning_rate: f64,
+    pub exploration: f64,
+    pub episodes: u32,
+}
+
+fn result_value(result: GameResult) -> f64 {
+    match result {
+        GameResult::Player(Player::X) => 1.0,
+        GameResult::Player(Player::O) => -1.0,
+        _ => 0.0,
+    }
+}
+
+pub fn random_move(board: &Board) -> u32 {
+    let moves = board.generate_moves();
+    let rng = rand::thread_rng().gen_range(0..moves.len());
+    *moves[rng].moves.last().unwrap()
+}
+
+impl QLearning {
+    pub fn new(size: u32, learning_rate: f64, exploration: f64, episodes: u32) -> Self {
+        Self {
+            values: HashMap::new(),
+            size,
+            learning_rate,
+            exploration,
+            episodes,
+        }
+    }
+
+    pub fn positions(&self) -> usize {
+        self.values.len()
+    }
+
+    fn value(&self, board: &Board) -> f64 {
+        match board.get_result() {
+            GameResult::InProgress => *self.values.get(board).unwrap_or(&0.0),
+            result => result_value(result),
+        }
+    }
+
+    // Index into `moves` of the afterstate that is best for the side to move.
+    fn greedy(&self, player: Player, moves: &[Board]) -> usize {
+        let mut best = 0;
+        for (index, board) in moves.iter().enumerate() {
+            let better = match player {
+                Player::X => self.value(board) > self.value(&moves[best]),
+                Player::O => self.value(board) < self.value(&moves[best]),
+            };
+            if better {
+                best = index;
+            }
+        }
+        best
+    }
+
+    pub fn train(&mut self) {
+        let mut rng = rand::thread_rng();
+        for _ in 0..self.episodes {
+            let mut board = Board::create_board(self.size);
+            let mut previous: Option<Board> = None;
+            while board.get_result() == GameResult::InProgress {
+                let moves = board.generate_moves();
+                let explore = rng.gen::<f64>() < self.exploration;
+                let chosen = if explore {
+                    rng.gen_range(0..moves.len())
+                } else {
+                    self.greedy(board.player_turn, &moves)
+                };
+                board = moves[chosen].clone();
+                // Exploratory moves say nothing about the value of the position before them
+                if let (false, Some(prev)) = (explore, &previous) {
+                    let old = self.value(prev);
+                    let target = self.value(&board);
+                    self.values
+                        .insert(prev.clone(), old + self.learning_rate * (target - old));
+                }
+                previous = Some(board.clone());
+            }
+        }
+    }
+
+    pub fn best_move(&self, board: &Board) -> u32 {
+        let moves = board.generate_moves();
+        let best = self.greedy(board.player_turn, &moves);
+        *moves[best].moves.last().unwrap()
+    }
+
+    // Plays `games` games against `opponent`, switching sides every game.
+    // Returns (wins, draws, losses) from the learned agent's point of view.
+    pub fn play_against(&self, opponent: fn(&Board) -> u32, games: u32) -> (u32, u32, u32) {
+        let (mut wins, mut draws, mut losses) = (0, 0, 0);
+        for game in 0..games {
+            let agent = if game % 2 == 0 { Player::X } else { Player::O };
+            let mut board = Board::create_board(self.size);
+            while board.get_result() == GameResult::InProgress {
+                let index = if board.player_turn == agent {
+                    self.best_move(&board)
+                } else {
+                    opponent(&board)
+                };
+                board.make_move(index, self.size * self.size).unwrap();
+            }
+            match board.get_result() {
+                GameResult::Player(p) if p == agent => wins += 1,
+                GameResult::Player(_) => losses += 1,
+                _ => draws += 1,
+            }
+        }
+        (wins, draws, losses)
+    }
+
+    // File format: first line is the board size, then one position per line,
+    // fields written as X, O or '.' followed by the value.
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut out = format!("{}\n", self.size);
+        for (board, value) in &self.values {
+            for field in &board.fields {
+                out.push(match field {
+                    Field::Player(Player::X) => 'X',
+                    Field::Player(Player::O) => 'O',
+                    Field::Free => '.',
+                });
+            }
+            out.push_str(&format!(" {}\n", value));
+        }
+        fs::write(path, out)
+    }
+
+    pub fn load(&mut self, path: &str) -> io::Result<()> {
+        let bad_data = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+        let content = fs::read_to_string(path)?;
+        let mut lines = content.lines();
+        let size: u32 = lines
+            .next()
+            .and_then(|l| l.trim().parse().ok())
+            .ok_or_else(|| bad_data("Missing board size"))?;
+        if size != self.size {
+            return Err(bad_data("Table was trained for a different board size"));
+        }
+        for line in lines {
+            let (fields, value) = line
+                .split_once(' ')
+                .ok_or_else(|| bad_data("Malformed line"))?;
+            let value: f64 = value.parse().map_err(|_| bad_data("Malformed value"))?;
+            let mut board = Board::create_board(size);
+            if fields.len() != board.fields.len() {
+                return Err(bad_data("Wrong number of fields"));
+            }
+            for (index, c) in fields.chars().enumerate() {
+                board.fields[index] = match c {
+                    'X' => Field::Player(Player::X),
+                    'O' => Field::Player(Player::O),
+                    '.' => Field::Free,
+                    _ => return Err(bad_data("Unknown field")),
+                };
+            }
+            self.values.insert(board, value);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,332 @@
+use crate::rules::GameRules;
+use crate::{lines, GameResult, Player};
+use rand::Rng;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QuantumMove {
+    // Spooky mark in two squares
+    Spooky(usize, usize),
+    // Square the mark that closed a cycle collapses into, chosen by the opponent
+    Collapse(usize),
+    // Only one square left, it gets a plain mark
+    Classical(usize),
+}
+
+// A mark of `player` placed on move `subscript`, in both squares until it collapses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct SpookyMark {
+    player: Player,
+    subscript: u8,
+    squares: [usize; 2],
+}
+
+// Quantum tic-tac-toe on 3x3. Squares and spooky marks form the entanglement graph, a
+// mark between two already connected squares closes a cycle and the opponent decides
+// how it collapses, which forces every mark connected to it into one square too. Lines
+// only count once all their marks are classical.
+#[derive(Clone)]
+pub struct Quantum {
+    classical: [Option<(Player, u8)>; 9],
+    spooky: Vec<SpookyMark>,
+    // Mark that closed a cycle and waits for Collapse
+    pending: Option<SpookyMark>,
+    player_turn: Player,
+    // Subscript of the next mark
+    subscript: u8,
+    // Positions before each move, for undo
+    history: Vec<Quantum>,
+}
+
+impl PartialEq for Quantum {
+    fn eq(&self, other: &Self) -> bool {
+        self.classical == other.classical
+            && self.spooky == other.spooky
+            && self.pending == other.pending
+            && self.player_turn == other.player_turn
+    }
+}
+
+impl Eq for Quantum {}
+
+impl Hash for Quantum {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.classical.hash(state);
+        self.spooky.hash(state);
+        self.pending.hash(state);
+        self.player_turn.hash(state);
+    }
+}
+
+impl Quantum {
+    pub fn create_game() -> Self {
+        Self {
+            classical: [None; 9],
+            spooky: vec![],
+            pending: None,
+            player_turn: Player::X,
+            subscript: 1,
+            history: vec![],
+        }
+    }
+
+    // Is there a path of spooky marks between the squares?
+    fn connected(&self, from: usize, to: usize) -> bool {
+        let mut reached = vec![from];
+        let mut index = 0;
+        while index < reached.len() {
+            let square = reached[index];
+            for mark in &self.spooky {
+                if let Some(position) = mark.squares.iter().position(|&s| s == square) {
+                    let other = mark.squares[1 - position];
+                    if !reached.contains(&other) {
+                        reached.push(other);
+                    }
+                }
+            }
+            index += 1;
+        }
+        reached.contains(&to)
+    }
+
+    // Makes `mark` classical in `square`, every other spooky mark in that square is
+    // pushed to its other square, and so on through the whole connected part
+    fn collapse(&mut self, mark: SpookyMark, square: usize) {
+        let mut work = vec![(mark, square)];
+        while let Some((mark, square)) = work.pop() {
+            self.spooky.retain(|m| *m != mark);
+            self.classical[square] = Some((mark.player, mark.subscript));
+            let (forced, rest): (Vec<SpookyMark>, Vec<SpookyMark>) =
+                self.spooky.iter().partition(|m| m.squares.contains(&square));
+            self.spooky = rest;
+            for m in forced {
+                let other = if m.squares[0] == square { m.squares[1] } else { m.squares[0] };
+                work.push((m, other));
+            }
+        }
+    }
+
+    // Highest subscript of every classical line of each player
+    fn lines_by_player(&self) -> (Vec<u8>, Vec<u8>) {
+        let mut x_lines = vec![];
+        let mut o_lines = vec![];
+        for line in lines(3, 3, 3) {
+            let marks: Vec<(Player, u8)> = line.iter().filter_map(|&s| self.classical[s]).collect();
+            if marks.len() == 3 && marks.iter().all(|m| m.0 == marks[0].0) {
+                let highest = marks.iter().map(|m| m.1).max().unwrap();
+                match marks[0].0 {
+                    Player::X => x_lines.push(highest),
+                    Player::O => o_lines.push(highest),
+                }
+            }
+        }
+        (x_lines, o_lines)
+    }
+
+    // Points of X and O. A collapse can finish lines for both players at once, then
+    // the line with the lower highest subscript was made first and scores 1, the other
+    // one 1/2. Two lines of one player from a single collapse score 2.
+    pub fn score(&self) -> (f64, f64) {
+        let (x_lines, o_lines) = self.lines_by_player();
+        match (x_lines.iter().min(), o_lines.iter().min()) {
+            (Some(x), Some(o)) if x < o => (1.0, 0.5),
+            (Some(_), Some(_)) => (0.5, 1.0),
+            (Some(_), None) => (x_lines.len().min(2) as f64, 0.0),
+            (None, Some(_)) => (0.0, o_lines.len().min(2) as f64),
+            (None, None) => (0.0, 0.0),
+        }
+    }
+
+    fn free_squares(&self) -> Vec<usize> {
+        (0..9).filter(|&s| self.classical[s].is_none()).collect()
+    }
+}
+
+impl GameRules for Quantum {
+    type Move = QuantumMove;
+
+    fn legal_moves(&self) -> Vec<QuantumMove> {
+        if let Some(mark) = self.pending {
+            return mark.squares.iter().map(|&s| QuantumMove::Collapse(s)).collect();
+        }
+        let free = self.free_squares();
+        if free.len() == 1 {
+            return vec![QuantumMove::Classical(free[0])];
+        }
+        let mut moves = vec![];
+        for (i, &a) in free.iter().enumerate() {
+            for &b in &free[i + 1..] {
+                moves.push(QuantumMove::Spooky(a, b));
+            }
+        }
+        moves
+    }
+
+    fn apply(&mut self, m: QuantumMove) -> Result<(), &'static str> {
+        if self.result() != GameResult::InProgress {
+            return Err("Game is already over!");
+        }
+        let mut next = self.clone();
+        next.history = vec![];
+        match (m, self.pending) {
+            (QuantumMove::Collapse(square), Some(mark)) => {
+                if !mark.squares.contains(&square) {
+                    return Err("Mark can't collapse into that square");
+                }
+                next.pending = None;
+                next.collapse(mark, square);
+                // The player who chose the collapse still makes their own move
+            }
+            (_, Some(_)) => return Err("Cycle has to be collapsed first!"),
+            (QuantumMove::Collapse(_), None) => return Err("Nothing to collapse"),
+            (QuantumMove::Spooky(a, b), None) => {
+                if a == b || a >= 9 || b >= 9 {
+                    return Err("Spooky mark needs two different squares");
+                }
+                if self.classical[a].is_some() || self.classical[b].is_some() {
+                    return Err("Square is already classical!");
+                }
+                let mark = SpookyMark {
+                    player: self.player_turn,
+                    subscript: self.subscript,
+                    squares: [a, b],
+                };
+                if next.connected(a, b) {
+                    next.pending = Some(mark);
+                }
+                next.spooky.push(mark);
+                next.subscript += 1;
+                next.player_turn = self.player_turn.opponent();
+            }
+            (QuantumMove::Classical(square), None) => {
+                if self.free_squares() != vec![square] {
+                    return Err("Classical moves only go to the last square");
+                }
+                next.classical[square] = Some((self.player_turn, self.subscript));
+                next.subscript += 1;
+                next.player_turn = self.player_turn.opponent();
+            }
+        }
+        next.history = std::mem::take(&mut self.history);
+        next.history.push(self.clone());
+        *self = next;
+        Ok(())
+    }
+
+    fn undo(&mut self) -> Result<(), &'static str> {
+        let mut previous = self.history.pop().ok_or("No moves has been played")?;
+        previous.history = std::mem::take(&mut self.history);
+        *self = previous;
+        Ok(())
+    }
+
+    fn result(&self) -> GameResult {
+        let (x, o) = self.score();
+        if x > o {
+            GameResult::Player(Player::X)
+        } else if o > x {
+            GameResult::Player(Player::O)
+        } else if self.pending.is_none() && self.free_squares().is_empty() {
+            GameResult::Draw
+        } else {
+            GameResult::InProgress
+        }
+    }
+
+    fn side_to_move(&self) -> Player {
+        self.player_turn
+    }
+}
+
+// Move picking player, for matches between the AIs
+pub type Engine<'a> = &'a dyn Fn(&Quantum) -> QuantumMove;
+
+pub fn random_move(game: &Quantum) -> QuantumMove {
+    let moves = game.legal_moves();
+    moves[rand::thread_rng().gen_range(0..moves.len())]
+}
+
+// Classical lines still open for X minus those open for O
+pub fn quantum_eval(game: &Quantum) -> i32 {
+    let mut result = 0;
+    for line in lines(3, 3, 3) {
+        let owners: Vec<Player> = line.iter().filter_map(|&s| game.classical[s]).map(|m| m.0).collect();
+        if owners.iter().all(|&p| p == Player::X) {
+            result += 1 + owners.len() as i32;
+        }
+        if owners.iter().all(|&p| p == Player::O) {
+            result -= 1 + owners.len() as i32;
+        }
+    }
+    result
+}
+
+// Classical marks in capitals, spooky ones in lower case, all with their subscript
+impl fmt::Debug for Quantum {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let cells: Vec<String> = (0..9)
+            .map(|square| match self.classical[square] {
+                Some((player, subscript)) => format!("{:?}{}", player, subscript),
+                None => self
+                    .spooky
+                    .iter()
+                    .filter(|m| m.squares.contains(&square))
+                    .map(|m| format!("{}{}", format!("{:?}", m.player).to_lowercase(), m.subscript))
+                    .collect::<Vec<String>>()
+                    .join(" "),
+            })
+            .collect();
+        let width = cells.iter().map(|c| c.len()).max().unwrap().max(2);
+        for row in 0..3 {
+            writeln!(f, "{}", "-".repeat(3 * (width + 3)))?;
+            let row_cells: Vec<String> =
+                (0..3).map(|column| format!(" {:<width$} ", cells[row * 3 + column], width = width)).collect();
+            writeln!(f, "{}", row_cells.join(" |"))?;
+        }
+        writeln!(f, "{}", "-".repeat(3 * (width + 3)))?;
+        if let Some(mark) = self.pending {
+            writeln!(f, "Cycle closed by {:?}{}, collapse it into {} or {}", mark.player, mark.subscript, mark.squares[0], mark.squares[1])?;
+        }
+        write!(f, "{:?} to move", self.player_turn)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::tests::undo_round_trip;
+
+    // X1 in 0 and 1, O2 in 1 and 2, X3 in 2 and 0 closes the cycle
+    fn closed_cycle() -> Quantum {
+        let mut game = Quantum::create_game();
+        for m in [QuantumMove::Spooky(0, 1), QuantumMove::Spooky(1, 2), QuantumMove::Spooky(0, 2)] {
+            game.apply(m).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn cycle_collapses_every_connected_mark() {
+        let mut game = closed_cycle();
+        assert_eq!(game.side_to_move(), Player::O);
+        assert_eq!(game.legal_moves(), vec![QuantumMove::Collapse(0), QuantumMove::Collapse(2)]);
+        assert_eq!(game.apply(QuantumMove::Spooky(3, 4)), Err("Cycle has to be collapsed first!"));
+        game.apply(QuantumMove::Collapse(0)).unwrap();
+        assert_eq!(game.classical[..3], [Some((Player::X, 3)), Some((Player::X, 1)), Some((Player::O, 2))]);
+        assert!(game.spooky.is_empty());
+        // The player who collapsed still moves
+        assert_eq!(game.side_to_move(), Player::O);
+    }
+
+    #[test]
+    fn undo_brings_the_cycle_back() {
+        let mut game = closed_cycle();
+        let before = game.clone();
+        game.apply(QuantumMove::Collapse(2)).unwrap();
+        game.undo().unwrap();
+        assert_eq!(game, before);
+        assert_eq!(game.spooky.len(), 3);
+        undo_round_trip(&Quantum::create_game(), 200, |g| g.clone());
+    }
+}
@@ -0,0 +1,305 @@
+use crate::cache::{bits_result, result_bits};
+use crate::retrograde::Database;
+use crate::{Board, Field, GameResult, Player};
+
+// Dense numbering of every position with a legal stone count (X moved first, so
+// X has as many stones as O or one more). Positions are grouped in blocks by
+// (x stones, o stones); inside a block the X fields are ranked as a combination of
+// all fields and the O fields as a combination of the fields X left free.
+pub struct Ranking {
+    // Empty board the unranked positions are filled into
+    start: Board,
+    cells: usize,
+    binomial: Vec<Vec<u64>>,
+    // (x stones, o stones, first index of the block)
+    blocks: Vec<(usize, usize, u64)>,
+    total: u64,
+}
+
+impl Ranking {
+    pub fn new(start: &Board) -> Self {
+        let cells = start.fields.len();
+        let mut binomial = vec![vec![0u64; cells + 1]; cells + 1];
+        for n in 0..=cells {
+            binomial[n][0] = 1;
+            for k in 1..=n {
+                binomial[n][k] = binomial[n - 1][k - 1] + binomial[n - 1][k];
+            }
+        }
+        let mut blocks = vec![];
+        let mut total = 0;
+        for stones in 0..=cells {
+            let x = stones.div_ceil(2);
+            let o = stones / 2;
+            blocks.push((x, o, total));
+            total += binomial[cells][x] * binomial[cells - x][o];
+        }
+        Self {
+            start: start.empty_like(),
+            cells,
+            binomial,
+            blocks,
+            total,
+        }
+    }
+
+    pub fn positions(&self) -> u64 {
+        self.total
+    }
+
+    // Colex rank of a sorted combination
+    fn combination_rank(&self, positions: &[usize]) -> u64 {
+        positions
+            .iter()
+            .enumerate()
+            .map(|(i, &c)| self.binomial[c][i + 1])
+            .sum()
+    }
+
+    fn combination_unrank(&self, mut rank: u64, k: usize, n: usize) -> Vec<usize> {
+        let mut result = vec![0; k];
+        let mut c = n;
+        for i in (1..=k).rev() {
+            c -= 1;
+            while self.binomial[c][i] > rank {
+                c -= 1;
+            }
+            result[i - 1] = c;
+            rank -= self.binomial[c][i];
+        }
+        result
+    }
+
+    pub fn rank_fields(&self, fields: &[Field]) -> u64 {
+        let mut x_fields = vec![];
+        let mut o_fields = vec![];
+        // O fields are numbered among the fields not taken by X
+        let mut not_x = 0;
+        for (index, &field) in fields.iter().enumerate() {
+            match field {
+                Field::Player(Player::X) => x_fields.push(index),
+                Field::Player(Player::O) => {
+                    o_fields.push(not_x);
+                    not_x += 1;
+                }
+                Field::Free => not_x += 1,
+            }
+        }
+        let (x, o, offset) = self.blocks[x_fields.len() + o_fields.len()];
+        assert!(x == x_fields.len() && o == o_fields.len(), "Illegal stone count");
+        offset
+            + self.combination_rank(&x_fields) * self.binomial[self.cells - x][o]
+            + self.combination_rank(&o_fields)
+    }
+
+    pub fn rank(&self, board: &Board) -> u64 {
+        self.rank_fields(&board.fields)
+    }
+
+    pub fn unrank(&self, index: u64) -> Board {
+        let block = self.blocks.iter().rposition(|&(_, _, offset)| offset <= index).unwrap();
+        let (x, o, offset) = self.blocks[block];
+        let o_count = self.binomial[self.cells - x][o];
+        let x_fields = self.combination_unrank((index - offset) / o_count, x, self.cells);
+        let o_fields = self.combination_unrank((index - offset) % o_count, o, self.cells - x);
+
+        let mut board = self.start.clone();
+        for &field in &x_fields {
+            board.fields[field] = Field::Player(Player::X);
+        }
+        let free: Vec<usize> = (0..self.cells).filter(|f| !x_fields.contains(f)).collect();
+        for &field in &o_fields {
+            board.fields[free[field]] = Field::Player(Player::O);
+        }
+        if x > o {
+            board.player_turn = Player::O;
+        }
+        board
+    }
+}
+
+// For each of the 8 symmetries of the square, the source field of every field,
+// the 4 rotations of rot90board and each of them mirrored.
+pub fn symmetries(size: u32) -> Vec<Vec<usize>> {
+    let n = size as usize;
+    let identity: Vec<usize> = (0..n * n).collect();
+    let rotate = |p: &Vec<usize>| {
+        let mut result = vec![0; n * n];
+        for i in 0..n {
+            for j in 0..n {
+                result[j * n + (n - 1 - i)] = p[i * n + j];
+            }
+        }
+        result
+    };
+    let mirror = |p: &Vec<usize>| {
+        let mut result = vec![0; n * n];
+        for i in 0..n {
+            for j in 0..n {
+                result[i * n + (n - 1 - j)] = p[i * n + j];
+            }
+        }
+        result
+    };
+    let mut result = vec![identity];
+    for _ in 0..3 {
+        let next = rotate(result.last().unwrap());
+        result.push(next);
+    }
+    for r in 0..4 {
+        let mirrored = mirror(&result[r]);
+        result.push(mirrored);
+    }
+    result
+}
+
+// On a torus every translation keeps the lines too: each symmetry of the square with
+// the board shifted by every row and column offset, 8 * size^2 in all, identity first.
+pub fn torus_symmetries(size: u32) -> Vec<Vec<usize>> {
+    let n = size as usize;
+    let square = symmetries(size);
+    let mut result = vec![];
+    for shift_row in 0..n {
+        for shift_col in 0..n {
+            for symmetry in &square {
+                let shifted = (0..n * n)
+                    .map(|f| symmetry[(f / n + shift_row) % n * n + (f % n + shift_col) % n])
+                    .collect();
+                result.push(shifted);
+            }
+        }
+    }
+    result
+}
+
+// Symmetries that keep the lines of `board`
+pub fn board_symmetries(board: &Board) -> Vec<Vec<usize>> {
+    if board.torus {
+        torus_symmetries(board.size)
+    } else {
+        symmetries(board.size)
+    }
+}
+
+// Smallest rank among all symmetric images of `fields`
+pub fn canonical_rank(ranking: &Ranking, symmetries: &[Vec<usize>], fields: &[Field]) -> u64 {
+    symmetries
+        .iter()
+        .map(|s| {
+            let transformed: Vec<Field> = s.iter().map(|&f| fields[f]).collect();
+            ranking.rank_fields(&transformed)
+        })
+        .min()
+        .unwrap()
+}
+
+// Ranking of symmetry classes: the index of a position is the position of its
+// canonical (smallest) rank in the sorted list of canonical ranks.
+pub struct SymmetricRanking {
+    pub ranking: Ranking,
+    symmetries: Vec<Vec<usize>>,
+    canonical: Vec<u64>,
+}
+
+impl SymmetricRanking {
+    pub fn new(start: &Board) -> Self {
+        let ranking = Ranking::new(start);
+        let symmetries = board_symmetries(start);
+        let mut result = Self {
+            ranking,
+            symmetries,
+            canonical: vec![],
+        };
+        let canonical = (0..result.ranking.positions())
+            .filter(|&index| result.canonical_rank(&result.ranking.unrank(index).fields) == index)
+            .collect();
+        result.canonical = canonical;
+        result
+    }
+
+    fn canonical_rank(&self, fields: &[Field]) -> u64 {
+        canonical_rank(&self.ranking, &self.symmetries, fields)
+    }
+
+    pub fn positions(&self) -> u64 {
+        self.canonical.len() as u64
+    }
+
+    pub fn rank(&self, board: &Board) -> u64 {
+        let canonical = self.canonical_rank(&board.fields);
+        self.canonical.binary_search(&canonical).unwrap() as u64
+    }
+
+    pub fn unrank(&self, index: u64) -> Board {
+        self.ranking.unrank(self.canonical[index as usize])
+    }
+}
+
+// Solved database as a flat array of 2 bit results, 4 positions per byte.
+// Indices that are not reachable positions stay InProgress.
+pub struct PackedTable {
+    values: Vec<u8>,
+}
+
+impl PackedTable {
+    pub fn new(len: u64) -> Self {
+        Self {
+            values: vec![0; (len as usize).div_ceil(4)],
+        }
+    }
+
+    pub fn from_database(database: &Database, rank: &dyn Fn(&Board) -> u64, len: u64) -> Self {
+        let mut table = Self::new(len);
+        for (board, entry) in database.boards() {
+            table.set(rank(board), entry.result);
+        }
+        table
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn get(&self, index: u64) -> GameResult {
+        let index = index as usize;
+        bits_result((self.values[index / 4] >> (2 * (index % 4))) & 3).unwrap()
+    }
+
+    pub fn set(&mut self, index: u64, result: GameResult) {
+        let index = index as usize;
+        let shift = 2 * (index % 4);
+        self.values[index / 4] = (self.values[index / 4] & !(3 << shift)) | (result_bits(result) << shift);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rank_round_trip() {
+        let symmetric = SymmetricRanking::new(&Board::create_board(3));
+        let plain = &symmetric.ranking;
+        assert_eq!(plain.positions(), 6046);
+        assert_eq!(symmetric.positions(), 850);
+        for index in 0..plain.positions() {
+            assert_eq!(plain.rank(&plain.unrank(index)), index);
+        }
+        for index in 0..symmetric.positions() {
+            assert_eq!(symmetric.rank(&symmetric.unrank(index)), index);
+        }
+    }
+
+    #[test]
+    fn packed_tables_match_the_database() {
+        let database = Database::solve(Board::create_board(3));
+        let symmetric = SymmetricRanking::new(&database.start);
+        let plain = &symmetric.ranking;
+        let table = PackedTable::from_database(&database, &|b| plain.rank(b), plain.positions());
+        let sym_table = PackedTable::from_database(&database, &|b| symmetric.rank(b), symmetric.positions());
+        for (board, entry) in database.boards() {
+            assert_eq!(table.get(plain.rank(board)), entry.result);
+            assert_eq!(sym_table.get(symmetric.rank(board)), entry.result);
+        }
+    }
+}
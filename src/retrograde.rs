@@ -0,0 +1,183 @@
+use crate::{Board, GameResult, Player};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Entry {
+    pub result: GameResult,
+    // Plies until the game ends with perfect play, the winner hurrying and the loser stalling
+    pub distance: u8,
+}
+
+// Search function under test, returns the GameResult of the position it is given
+pub type Engine = Box<dyn FnMut(&mut Board) -> GameResult>;
+
+// Perfect play database of every position reachable from the empty board.
+pub struct Database {
+    // Empty board of the solved game
+    pub start: Board,
+    entries: HashMap<Board, Entry>,
+}
+
+// Is `a` a better outcome than `b` for `player`, who picks between them?
+pub fn better(player: Player, a: Entry, b: Entry) -> bool {
+    let (a_score, b_score) = match player {
+        Player::X => (a.result, b.result),
+        Player::O => (b.result, a.result),
+    };
+    if a_score != b_score {
+        return a_score > b_score;
+    }
+    match a.result {
+        GameResult::Player(winner) if winner != player => a.distance > b.distance,
+        _ => a.distance < b.distance,
+    }
+}
+
+impl Database {
+    // Enumerates the positions ply by ply, then labels them backwards: terminal
+    // positions get their get_result, every other position the best of its children,
+    // which all sit in the next ply and are therefore already labeled.
+    pub fn solve(start: Board) -> Self {
+        let mut layers = vec![vec![start.clone()]];
+        loop {
+            let mut next = HashSet::new();
+            for board in layers.last().unwrap() {
+                if board.get_result() != GameResult::InProgress {
+                    continue;
+                }
+                for mut child in board.generate_moves() {
+                    // Positions are keyed by fields only, no need to keep the move list
+                    child.moves = vec![];
+                    child.turns = vec![];
+                    next.insert(child);
+                }
+            }
+            if next.is_empty() {
+                break;
+            }
+            layers.push(next.into_iter().collect());
+        }
+
+        let mut entries = HashMap::new();
+        for layer in layers.into_iter().rev() {
+            for board in layer {
+                let entry = match board.get_result() {
+                    GameResult::InProgress => {
+                        let mut best: Option<Entry> = None;
+                        for child in board.generate_moves() {
+                            let child_entry = entries[&child];
+                            if best.is_none_or(|b| better(board.player_turn, child_entry, b)) {
+                                best = Some(child_entry);
+                            }
+                        }
+                        let best = best.unwrap();
+                        Entry {
+                            result: best.result,
+                            distance: best.distance + 1,
+                        }
+                    }
+                    result => Entry {
+                        result,
+                        distance: 0,
+                    },
+                };
+                entries.insert(board, entry);
+            }
+        }
+        Self { start, entries }
+    }
+
+    pub fn get(&self, board: &Board) -> Option<Entry> {
+        self.entries.get(board).copied()
+    }
+
+    pub fn positions(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn boards(&self) -> impl Iterator<Item = (&Board, &Entry)> {
+        self.entries.iter()
+    }
+
+    pub fn best_move(&self, board: &Board) -> u32 {
+        let mut best: Option<(u32, Entry)> = None;
+        for child in board.generate_moves() {
+            let entry = self.entries[&child];
+            if best.is_none_or(|(_, b)| better(board.player_turn, entry, b)) {
+                best = Some((*child.moves.last().unwrap(), entry));
+            }
+        }
+        best.unwrap().0
+    }
+
+    // Runs `engine` on every position (or only the empty board when there are more than
+    // `limit` of them) and returns how many results disagree with the database.
+    pub fn verify(&self, engine: &mut Engine, limit: usize) -> usize {
+        let mut mismatches = 0;
+        if self.positions() > limit {
+            let mut board = self.start.clone();
+            let expected = self.entries[&board].result;
+            return if engine(&mut board) == expected { 0 } else { 1 };
+        }
+        for (board, entry) in &self.entries {
+            if engine(&mut board.clone()) != entry.result {
+                mismatches += 1;
+            }
+        }
+        mismatches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{alpha_beta, alpha_beta_lookup, alpha_beta_lookup_sym, min_max};
+
+    #[test]
+    fn solves_3x3_as_a_draw() {
+        let database = Database::solve(Board::create_board(3));
+        assert_eq!(database.positions(), 5478);
+        let root = database.get(&database.start).unwrap();
+        assert_eq!(root.result, GameResult::Draw);
+        assert_eq!(root.distance, 9);
+    }
+
+    #[test]
+    fn engines_agree_with_the_database() {
+        let alfa = GameResult::Player(Player::O);
+        let beta = GameResult::Player(Player::X);
+        let mut engines: Vec<Engine> = vec![
+            Box::new(|b: &mut Board| min_max(b, 10, b.player_turn).result),
+            Box::new(move |b: &mut Board| alpha_beta(b, 10, alfa, beta, b.player_turn).result),
+            Box::new(move |b: &mut Board| {
+                alpha_beta_lookup(b, 10, alfa, beta, b.player_turn, &mut HashMap::new()).result
+            }),
+            Box::new(move |b: &mut Board| {
+                alpha_beta_lookup_sym(b, 10, alfa, beta, b.player_turn, &mut HashMap::new()).result
+            }),
+        ];
+        for start in [Board::create_board(3), Board::create_misere_board(3, 3)] {
+            let database = Database::solve(start);
+            for engine in engines.iter_mut() {
+                assert_eq!(database.verify(engine, 20000), 0);
+            }
+        }
+    }
+
+    // One table kept across all searches, the way pondering and the cache use it, must
+    // only ever hold real results
+    #[test]
+    fn shared_lookup_holds_exact_results() {
+        let database = Database::solve(Board::create_board(3));
+        let alfa = GameResult::Player(Player::O);
+        let beta = GameResult::Player(Player::X);
+        let mut lookup = HashMap::new();
+        for (board, entry) in database.boards() {
+            let result = alpha_beta_lookup(&mut board.clone(), 10, alfa, beta, board.player_turn, &mut lookup).result;
+            assert_eq!(result, entry.result);
+        }
+        for (board, result) in &lookup {
+            assert_eq!(database.get(board).unwrap().result, *result);
+        }
+    }
+}
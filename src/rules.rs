@@ -0,0 +1,131 @@
+use crate::ranking::torus_symmetries;
+use crate::{Board, GameResult, Player};
+use std::fmt;
+use std::hash::Hash;
+
+// Everything the search engines need to know about a turn based game. Positions are
+// compared and hashed as a whole, so implementors can be used as lookup table keys.
+pub trait GameRules: Clone + Eq + Hash {
+    type Move: Copy + PartialEq + fmt::Debug;
+
+    fn legal_moves(&self) -> Vec<Self::Move>;
+    fn apply(&mut self, m: Self::Move) -> Result<(), &'static str>;
+    fn undo(&mut self) -> Result<(), &'static str>;
+    fn result(&self) -> GameResult;
+    fn side_to_move(&self) -> Player;
+
+    // Moves in the order alpha-beta should try them, best guess first
+    fn ordered_moves(&self) -> Vec<Self::Move> {
+        self.legal_moves()
+    }
+
+    // Other positions with the same value, checked by the _sym lookups
+    fn symmetries(&self) -> Vec<Self> {
+        vec![]
+    }
+}
+
+impl GameRules for Board {
+    type Move = u32;
+
+    fn legal_moves(&self) -> Vec<u32> {
+        self.free_moves()
+    }
+
+    fn apply(&mut self, m: u32) -> Result<(), &'static str> {
+        self.make_move(m, self.size * self.size)
+    }
+
+    fn undo(&mut self) -> Result<(), &'static str> {
+        self.undo_last_move()
+    }
+
+    fn result(&self) -> GameResult {
+        self.get_result()
+    }
+
+    fn side_to_move(&self) -> Player {
+        self.player_turn
+    }
+
+    fn ordered_moves(&self) -> Vec<u32> {
+        self.generate_sorted_lines_heuristic()
+            .iter()
+            .map(|b| *b.moves.last().unwrap())
+            .collect()
+    }
+
+    fn symmetries(&self) -> Vec<Self> {
+        if self.gravity {
+            return vec![self.mirror_board()];
+        }
+        if self.torus {
+            return torus_symmetries(self.size)[1..]
+                .iter()
+                .map(|symmetry| Self {
+                    fields: symmetry.iter().map(|&f| self.fields[f]).collect(),
+                    ..self.clone()
+                })
+                .collect();
+        }
+        let rot90 = self.rot90board();
+        let rot180 = rot90.rot90board();
+        let rot270 = rot180.rot90board();
+        vec![rot90, rot180, rot270]
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::best_move_alpha_beta;
+    use crate::retrograde::Database;
+    use rand::Rng;
+    use std::collections::HashMap;
+
+    // Plays `games` random games from `start` to the end, then takes every move back,
+    // checking that each undo gives back the `state` from before that move
+    pub fn undo_round_trip<G: GameRules, T: PartialEq + fmt::Debug>(start: &G, games: usize, state: impl Fn(&G) -> T) {
+        let mut rng = rand::thread_rng();
+        for _ in 0..games {
+            let mut game = start.clone();
+            let mut states = vec![];
+            while game.result() == GameResult::InProgress {
+                states.push(state(&game));
+                let moves = game.legal_moves();
+                game.apply(moves[rng.gen_range(0..moves.len())]).unwrap();
+            }
+            while let Some(before) = states.pop() {
+                game.undo().unwrap();
+                assert_eq!(state(&game), before);
+            }
+            assert!(game.undo().is_err());
+        }
+    }
+
+    // Checked on every 3x3 position: ordered_moves only reorders the legal moves,
+    // symmetric positions have the same value and the generic search keeps the value
+    #[test]
+    fn board_rules_agree_with_the_database() {
+        let database = Database::solve(Board::create_board(3));
+        let mut lookup = HashMap::new();
+        for (board, entry) in database.boards() {
+            let mut ordered = board.ordered_moves();
+            ordered.sort();
+            assert_eq!(ordered, board.legal_moves());
+            for symmetric in board.symmetries() {
+                assert_eq!(database.get(&symmetric).unwrap().result, entry.result);
+            }
+            if board.result() == GameResult::InProgress {
+                let mut child = board.clone();
+                child.apply(best_move_alpha_beta(board, &mut lookup)).unwrap();
+                assert_eq!(database.get(&child).unwrap().result, entry.result);
+            }
+        }
+    }
+
+    #[test]
+    fn board_undo_round_trip() {
+        undo_round_trip(&Board::create_board(3), 100, |b| (b.fields.clone(), b.player_turn));
+    }
+}
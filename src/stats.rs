@@ -0,0 +1,108 @@
+use crate::ranking::{board_symmetries, canonical_rank, Ranking};
+use crate::{Board, GameResult, Player};
+use std::collections::HashSet;
+
+// Numbers about the full game tree of a board configuration. Games are counted
+// along every path of the tree, positions once however many paths reach them.
+pub struct TreeStats {
+    pub games: u64,
+    // Indexed by the number of moves played when the game ended
+    pub x_wins_by_ply: Vec<u64>,
+    pub o_wins_by_ply: Vec<u64>,
+    pub draws_by_ply: Vec<u64>,
+    pub positions: usize,
+    pub symmetric_positions: usize,
+    pub terminal_x_wins: usize,
+    pub terminal_o_wins: usize,
+    pub terminal_draws: usize,
+    // Sum of the move counts of all non terminal nodes of the tree, and their number
+    children: u64,
+    inner_nodes: u64,
+}
+
+impl TreeStats {
+    pub fn average_branching(&self) -> f64 {
+        self.children as f64 / self.inner_nodes as f64
+    }
+}
+
+fn walk(board: &mut Board, stats: &mut TreeStats, positions: &mut HashSet<Board>) {
+    positions.insert(board.clone());
+    let ply = board.moves.len();
+    match board.get_result() {
+        GameResult::Player(Player::X) => stats.x_wins_by_ply[ply] += 1,
+        GameResult::Player(Player::O) => stats.o_wins_by_ply[ply] += 1,
+        GameResult::Draw => stats.draws_by_ply[ply] += 1,
+        GameResult::InProgress => {
+            let possible_moves = board.free_moves();
+            stats.inner_nodes += 1;
+            stats.children += possible_moves.len() as u64;
+            for legal_move in possible_moves {
+                board
+                    .make_move(legal_move, board.size * board.size)
+                    .unwrap();
+                walk(board, stats, positions);
+                board.undo_last_move().unwrap();
+            }
+            return;
+        }
+    }
+    stats.games += 1;
+}
+
+// Walks every game from the empty board. Only feasible for 3x3 and smaller,
+// the 4x4 tree has trillions of games.
+pub fn tree_stats(start: &Board) -> TreeStats {
+    let cells = start.fields.len();
+    let mut stats = TreeStats {
+        games: 0,
+        x_wins_by_ply: vec![0; cells + 1],
+        o_wins_by_ply: vec![0; cells + 1],
+        draws_by_ply: vec![0; cells + 1],
+        positions: 0,
+        symmetric_positions: 0,
+        terminal_x_wins: 0,
+        terminal_o_wins: 0,
+        terminal_draws: 0,
+        children: 0,
+        inner_nodes: 0,
+    };
+    let mut positions = HashSet::new();
+    walk(&mut start.empty_like(), &mut stats, &mut positions);
+
+    for board in &positions {
+        match board.get_result() {
+            GameResult::Player(Player::X) => stats.terminal_x_wins += 1,
+            GameResult::Player(Player::O) => stats.terminal_o_wins += 1,
+            GameResult::Draw => stats.terminal_draws += 1,
+            GameResult::InProgress => {}
+        }
+    }
+    let ranking = Ranking::new(start);
+    let symmetries = board_symmetries(start);
+    let canonical: HashSet<u64> = positions
+        .iter()
+        .map(|board| canonical_rank(&ranking, &symmetries, &board.fields))
+        .collect();
+    stats.positions = positions.len();
+    stats.symmetric_positions = canonical.len();
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The well known numbers of the 3x3 game
+    #[test]
+    fn tree_stats_3x3() {
+        let tree = tree_stats(&Board::create_board(3));
+        assert_eq!(tree.games, 255168);
+        assert_eq!(tree.x_wins_by_ply, [0, 0, 0, 0, 0, 1440, 0, 47952, 0, 81792]);
+        assert_eq!(tree.o_wins_by_ply, [0, 0, 0, 0, 0, 0, 5328, 0, 72576, 0]);
+        assert_eq!(tree.draws_by_ply, [0, 0, 0, 0, 0, 0, 0, 0, 0, 46080]);
+        assert_eq!(tree.positions, 5478);
+        assert_eq!(tree.symmetric_positions, 765);
+        assert_eq!((tree.terminal_x_wins, tree.terminal_o_wins, tree.terminal_draws), (626, 316, 16));
+    }
+}
@@ -0,0 +1,179 @@
+use crate::rules::GameRules;
+use crate::{Board, Field, GameResult, Player};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+// Ultimate tic-tac-toe: nine 3x3 boards laid out as a 3x3 meta board. A move in field
+// f of any sub-board sends the opponent to sub-board f, unless that one is already
+// finished, then they may play in any unfinished sub-board. Winning a sub-board claims
+// its field on the meta board, three claimed fields in a line win the game.
+#[derive(Clone)]
+pub struct Ultimate {
+    boards: Vec<Board>,
+    player_turn: Player,
+    // (sub-board, field) of every move, the last one decides where the next goes
+    moves: Vec<(usize, usize)>,
+}
+
+impl PartialEq for Ultimate {
+    fn eq(&self, other: &Self) -> bool {
+        self.boards == other.boards && self.target() == other.target()
+    }
+}
+
+impl Eq for Ultimate {}
+
+impl Hash for Ultimate {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.boards.hash(state);
+        self.target().hash(state);
+    }
+}
+
+impl Ultimate {
+    pub fn create_game() -> Self {
+        Self {
+            boards: vec![Board::create_board(3); 9],
+            player_turn: Player::X,
+            moves: vec![],
+        }
+    }
+
+    // Sub-board the next move has to go to, None when any unfinished one is allowed
+    pub fn target(&self) -> Option<usize> {
+        let &(_, field) = self.moves.last()?;
+        if self.boards[field].get_result() == GameResult::InProgress {
+            Some(field)
+        } else {
+            None
+        }
+    }
+
+    // The sub-board results as a 3x3 board, a field is taken by whoever won that sub-board
+    pub fn meta_board(&self) -> Board {
+        let mut meta = Board::create_board(3);
+        for (index, board) in self.boards.iter().enumerate() {
+            if let GameResult::Player(winner) = board.get_result() {
+                meta.fields[index] = Field::Player(winner);
+            }
+        }
+        meta
+    }
+}
+
+impl GameRules for Ultimate {
+    type Move = (usize, usize);
+
+    fn legal_moves(&self) -> Vec<(usize, usize)> {
+        let boards = match self.target() {
+            Some(target) => vec![target],
+            None => (0..9).collect(),
+        };
+        boards
+            .into_iter()
+            .filter(|&b| self.boards[b].get_result() == GameResult::InProgress)
+            .flat_map(|b| self.boards[b].legal_moves().into_iter().map(move |f| (b, f as usize)))
+            .collect()
+    }
+
+    fn apply(&mut self, (board, field): (usize, usize)) -> Result<(), &'static str> {
+        if board >= 9 {
+            return Err("Wrong board number");
+        }
+        if self.target().is_some_and(|target| target != board) {
+            return Err("Move has to be played in the target board!");
+        }
+        if self.boards[board].get_result() != GameResult::InProgress {
+            return Err("Board is already finished!");
+        }
+        // Sub-boards don't alternate on their own, the mover is decided here
+        self.boards[board].player_turn = self.player_turn;
+        self.boards[board].make_move(field as u32, 9)?;
+        self.moves.push((board, field));
+        self.player_turn = self.player_turn.opponent();
+        Ok(())
+    }
+
+    fn undo(&mut self) -> Result<(), &'static str> {
+        let &(board, _) = self.moves.last().ok_or("No moves has been played")?;
+        self.boards[board].undo_last_move()?;
+        self.moves.pop();
+        self.player_turn = self.player_turn.opponent();
+        Ok(())
+    }
+
+    fn result(&self) -> GameResult {
+        match self.meta_board().get_result() {
+            GameResult::Player(winner) => GameResult::Player(winner),
+            _ if self.boards.iter().all(|b| b.get_result() != GameResult::InProgress) => GameResult::Draw,
+            _ => GameResult::InProgress,
+        }
+    }
+
+    fn side_to_move(&self) -> Player {
+        self.player_turn
+    }
+}
+
+// Open lines of the meta board weigh much more than the open lines inside sub-boards
+pub fn ultimate_eval(game: &Ultimate) -> i32 {
+    let inner: i32 = game
+        .boards
+        .iter()
+        .filter(|b| b.get_result() == GameResult::InProgress)
+        .map(|b| b.lines_heuristic(Player::X))
+        .sum();
+    20 * game.meta_board().lines_heuristic(Player::X) + inner
+}
+
+// The 9x9 grid, sub-boards separated by double lines, followed by the meta board
+impl fmt::Debug for Ultimate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in 0..9 {
+            if row % 3 == 0 {
+                writeln!(f, "{}", "=".repeat(41))?;
+            }
+            for column in 0..9 {
+                let board = (row / 3) * 3 + column / 3;
+                let field = (row % 3) * 3 + column % 3;
+                let separator = if column % 3 == 0 { "||" } else { "|" };
+                write!(f, "{} {:?} ", separator, self.boards[board].fields[field])?;
+            }
+            writeln!(f, "||")?;
+        }
+        writeln!(f, "{}", "=".repeat(41))?;
+        match self.target() {
+            Some(target) => writeln!(f, "Next move in board {}", target)?,
+            None => writeln!(f, "Next move in any open board")?,
+        }
+        write!(f, "Meta board:{:?}", self.meta_board())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::tests::undo_round_trip;
+
+    #[test]
+    fn moves_send_the_opponent_to_their_field() {
+        let mut game = Ultimate::create_game();
+        game.apply((4, 2)).unwrap();
+        assert_eq!(game.target(), Some(2));
+        assert_eq!(game.apply((3, 0)), Err("Move has to be played in the target board!"));
+        assert!(game.legal_moves().iter().all(|&(board, _)| board == 2));
+        // X wins sub-board 0 with its top row, sent there O may go anywhere else
+        for m in [(2, 0), (0, 0), (0, 3), (3, 3), (3, 0), (0, 1), (1, 0), (0, 2), (2, 4), (4, 0)] {
+            game.apply(m).unwrap();
+        }
+        assert_eq!(game.meta_board().fields[0], Field::Player(Player::X));
+        assert_eq!(game.target(), None);
+        assert!(game.legal_moves().iter().all(|&(board, _)| board != 0));
+        assert_eq!(game.apply((0, 5)), Err("Board is already finished!"));
+    }
+
+    #[test]
+    fn undo_takes_back_single_moves() {
+        undo_round_trip(&Ultimate::create_game(), 20, |g| g.clone());
+    }
+}
@@ -0,0 +1,188 @@
+use crate::rules::GameRules;
+use crate::{Board, Field, GameResult, Player};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+// A wild move places either symbol, whoever moves decides which one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WildMove {
+    pub field: u32,
+    pub symbol: Player,
+}
+
+// Wild tic-tac-toe: both players place X or O as they like and whoever completes a line
+// of either symbol wins. The fields live in a Board, `player_turn` here is the player
+// to move, the Board's own player_turn is only used to place the chosen symbol.
+#[derive(Clone)]
+pub struct Wild {
+    pub board: Board,
+    player_turn: Player,
+    moves: Vec<WildMove>,
+}
+
+// Every move places one symbol, so the fields decide whose turn it is
+impl PartialEq for Wild {
+    fn eq(&self, other: &Self) -> bool {
+        self.board == other.board
+    }
+}
+
+impl Eq for Wild {}
+
+impl Hash for Wild {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.board.hash(state);
+    }
+}
+
+impl Wild {
+    pub fn create_game(n: u32, k: u32) -> Self {
+        Self {
+            board: Board::create_board_k(n, k),
+            player_turn: Player::X,
+            moves: vec![],
+        }
+    }
+
+    // Lines through `field` that would hold win_length - 1 copies of `symbol` and
+    // `field` itself, split into lines the move completes and lines it leaves one
+    // short with the last field free (a win handed to the opponent).
+    fn line_counts(&self, field: usize, symbol: Player) -> (usize, usize) {
+        let mut completed = 0;
+        let mut handed_over = 0;
+        for line in self.board.winning_lines().iter().filter(|l| l.contains(&field)) {
+            let others: Vec<Field> = line.iter().filter(|&&f| f != field).map(|&f| self.board.fields[f]).collect();
+            let same = others.iter().filter(|&&f| f == Field::Player(symbol)).count();
+            let free = others.iter().filter(|&&f| f == Field::Free).count();
+            if same == others.len() {
+                completed += 1;
+            } else if same == others.len() - 1 && free == 1 {
+                handed_over += 1;
+            }
+        }
+        (completed, handed_over)
+    }
+}
+
+impl GameRules for Wild {
+    type Move = WildMove;
+
+    // Both symbols on every free field, twice the moves of plain tic-tac-toe
+    fn legal_moves(&self) -> Vec<WildMove> {
+        self.board
+            .free_moves()
+            .into_iter()
+            .flat_map(|field| [Player::X, Player::O].map(|symbol| WildMove { field, symbol }))
+            .collect()
+    }
+
+    fn apply(&mut self, m: WildMove) -> Result<(), &'static str> {
+        self.board.player_turn = m.symbol;
+        self.board.make_move(m.field, self.board.size * self.board.size)?;
+        self.moves.push(m);
+        self.player_turn = self.player_turn.opponent();
+        Ok(())
+    }
+
+    fn undo(&mut self) -> Result<(), &'static str> {
+        self.board.undo_last_move()?;
+        self.moves.pop();
+        self.player_turn = self.player_turn.opponent();
+        Ok(())
+    }
+
+    // A line can only be completed by the last move, so its symbol doesn't matter,
+    // the player who made that move wins
+    fn result(&self) -> GameResult {
+        match self.board.get_result() {
+            GameResult::Player(_) => GameResult::Player(self.player_turn.opponent()),
+            result => result,
+        }
+    }
+
+    fn side_to_move(&self) -> Player {
+        self.player_turn
+    }
+
+    // Winning moves first, moves that leave the opponent a win last
+    fn ordered_moves(&self) -> Vec<WildMove> {
+        let mut moves: Vec<(WildMove, i32)> = self
+            .legal_moves()
+            .into_iter()
+            .map(|m| {
+                let (completed, handed_over) = self.line_counts(m.field as usize, m.symbol);
+                let score = if completed > 0 {
+                    2
+                } else if handed_over > 0 {
+                    0
+                } else {
+                    1
+                };
+                (m, score)
+            })
+            .collect();
+        moves.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+        moves.into_iter().map(|(m, _)| m).collect()
+    }
+
+    fn symmetries(&self) -> Vec<Self> {
+        self.board
+            .symmetries()
+            .into_iter()
+            .map(|board| Self {
+                board,
+                moves: vec![],
+                ..self.clone()
+            })
+            .collect()
+    }
+}
+
+// Open lines are dangerous for whoever has to move next, so the position is only
+// scored by how many moves are still safe for the side to move
+pub fn wild_eval(wild: &Wild) -> i32 {
+    let safe = wild
+        .legal_moves()
+        .iter()
+        .filter(|m| wild.line_counts(m.field as usize, m.symbol).1 == 0)
+        .count() as i32;
+    match wild.player_turn {
+        Player::X => safe,
+        Player::O => -safe,
+    }
+}
+
+impl fmt::Debug for Wild {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.board)?;
+        write!(f, "{:?} to move", self.player_turn)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::tests::undo_round_trip;
+
+    fn play(moves: &[(u32, Player)]) -> Wild {
+        let mut game = Wild::create_game(3, 3);
+        for &(field, symbol) in moves {
+            game.apply(WildMove { field, symbol }).unwrap();
+        }
+        game
+    }
+
+    // A line of O completed by either player wins for that player
+    #[test]
+    fn mover_wins_whatever_the_symbol() {
+        let o_completes = play(&[(0, Player::O), (4, Player::X), (1, Player::O), (2, Player::O)]);
+        assert_eq!(o_completes.result(), GameResult::Player(Player::O));
+        let x_completes = play(&[(0, Player::O), (4, Player::X), (1, Player::O), (8, Player::X), (2, Player::O)]);
+        assert_eq!(x_completes.result(), GameResult::Player(Player::X));
+    }
+
+    #[test]
+    fn undo_frees_the_field() {
+        undo_round_trip(&Wild::create_game(3, 3), 200, |g| (g.board.fields.clone(), g.side_to_move()));
+    }
+}